@@ -0,0 +1,106 @@
+// The game's content: its currencies, producers, manual actions and the world events that can happen to them.
+// Everything here refers to other content by key, so it can be defined before any of it is added to an engine
+
+use crate::continuum::{Cost, Currency, ManualAction, Offer, ProducerEntity, ProductType, WorldEffect, WorldEvent};
+
+pub fn currencies() -> Vec<Currency> {
+    vec![
+        Currency { id: "coins".to_string(), name: "Gold coins".to_string() },
+        Currency { id: "research".to_string(), name: "Research points".to_string() },
+        Currency { id: "shards".to_string(), name: "Prestige shards".to_string() },
+    ]
+}
+
+/// How much of the second currency one unit of the first buys at the exchange
+pub fn exchange_rates() -> Vec<(&'static str, &'static str, f64)> {
+    vec![
+        ("coins", "research", 0.1),
+        ("research", "coins", 5.0),
+        ("shards", "coins", 100.0),
+    ]
+}
+
+pub fn producers() -> Vec<ProducerEntity> {
+    vec![
+        ProducerEntity {
+            id: "gold".to_string(),
+            name: "Gold Miner".to_string(),
+            base_cost: vec![Cost::new("coins", 1.0)],
+            cost_coefficient: 1.03,
+            product_type: gold(0.01),
+            production_time_ms: 1000,
+            time_elapsed: 0,
+            count: 0,
+        },
+        ProducerEntity {
+            id: "wood".to_string(),
+            name: "Wood Cutter".to_string(),
+            base_cost: vec![Cost::new("coins", 1.0)],
+            cost_coefficient: 1.04,
+            product_type: ProductType {
+                id: "wood".to_string(),
+                name: "Wood".to_string(),
+                production_quantity: 1.0,
+                value_per_unit: 2.0,
+                currency: "coins".to_string(),
+            },
+            production_time_ms: 500,
+            time_elapsed: 0,
+            count: 0,
+        },
+    ]
+}
+
+/// Gold, worth a Gold coin a unit, made `production_quantity` at a time
+fn gold(production_quantity: f64) -> ProductType {
+    ProductType {
+        id: "gold".to_string(),
+        name: "Gold".to_string(),
+        production_quantity,
+        value_per_unit: 1.0,
+        currency: "coins".to_string(),
+    }
+}
+
+/// What the player can do by hand, before the producers take over
+pub fn actions() -> Vec<ManualAction> {
+    vec![
+        ManualAction {
+            id: "mine".to_string(),
+            name: "Mine Gold".to_string(),
+            product_type: gold(0.05),
+            cooldown_ms: 500,
+        },
+    ]
+}
+
+/// The table world events are drawn from. Events with a higher weight happen more often
+pub fn world_events() -> Vec<WorldEvent> {
+    vec![
+        WorldEvent {
+            id: "gold-rush".to_string(),
+            name: "Gold Rush".to_string(),
+            description: "Prospectors have struck a rich seam: 3x Gold for 60 seconds".to_string(),
+            weight: 3,
+            effect: WorldEffect::Boost { product: "gold".to_string(), multiplier: 3.0, duration_ms: 60_000 },
+        },
+        WorldEvent {
+            id: "forest-fire".to_string(),
+            name: "Forest Fire".to_string(),
+            description: "A forest fire has burnt a quarter of your Wood".to_string(),
+            weight: 2,
+            effect: WorldEffect::Destroy { product: "wood".to_string(), fraction: 0.25 },
+        },
+        WorldEvent {
+            id: "merchant".to_string(),
+            name: "Travelling Merchant".to_string(),
+            description: "A travelling merchant is in town, trading Research points for Gold coins".to_string(),
+            weight: 1,
+            effect: WorldEffect::Offer(Offer {
+                give: vec![Cost::new("coins", 20.0)],
+                receive: vec![Cost::new("research", 5.0)],
+                duration_ms: 30_000,
+            }),
+        },
+    ]
+}
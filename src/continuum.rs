@@ -1,6 +1,30 @@
 pub mod engine;
+pub mod commands;
 pub mod timer;
 pub mod entities;
+pub mod currency;
+pub mod error;
+pub mod events;
+pub mod snapshot;
+pub mod registry;
+pub mod storage;
+pub mod simulation;
+pub mod replay;
+pub mod optimizer;
+pub mod rng;
+pub mod statistics;
+pub mod world;
 
-pub use engine::{Engine, EngineConfig};
-pub use entities::{Producer, ProductType, ProducerEntity};
+pub use commands::{EngineCommand, PendingCommand};
+pub use currency::{Cost, Currency, Price};
+pub use engine::{Engine, EngineConfig, EngineMode};
+pub use entities::{Buff, BuffEffect, ManualAction, ProductType, ProducerEntity, UpgradeTarget};
+pub use error::Error;
+pub use events::EngineEvent;
+pub use optimizer::{Optimizer, Strategy};
+pub use registry::{ActionId, CurrencyId, ProducerId, Registry};
+pub use replay::Replay;
+pub use simulation::Simulation;
+pub use snapshot::Snapshot;
+pub use statistics::Statistics;
+pub use world::{Offer, WorldEffect, WorldEvent};
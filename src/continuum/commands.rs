@@ -0,0 +1,84 @@
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::continuum::entities::{Buff, Upgrade};
+use crate::continuum::error::{Error, Result};
+use crate::continuum::currency::Price;
+use crate::continuum::registry::{ActionId, CurrencyId, ProducerId};
+
+/// A change to the engine's state, sent with `Engine::send()`.
+/// Commands are queued and applied in the order they were sent, at the start of the next tick
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EngineCommand {
+    /// Buy `count` units of a producer, at most `MAX_BUY_COUNT`. Nothing is bought unless all of them can be paid for
+    Buy { id: ProducerId, count: u64 },
+    /// Sell `count` units of a producer for `EngineConfig::sell_refund_ratio` of what they cost
+    Sell { id: ProducerId, count: u64 },
+    /// Remove a producer from the engine, refunding every unit that is owned as if it were sold
+    Remove(ProducerId),
+    /// Pay for an upgrade and apply it to its producer or manual action
+    ApplyUpgrade(Upgrade),
+    /// Use a manual action, e.g. when the player clicks "Mine Gold"
+    PerformAction(ActionId),
+    /// Pay for a buff and activate it
+    BuyBuff(Buff),
+    /// Give up every producer, product, upgrade and the wallet in exchange for a permanent
+    /// `EngineConfig::prestige_bonus` to the value of everything produced
+    Prestige,
+    /// Run the game faster or slower than real time, e.g. 2.0 for double speed
+    SetTimeScale(f64),
+    /// Fast-forward the game by `elapsed_ms` of real time at once, e.g. to grant progress made while it wasn't running
+    Advance { elapsed_ms: u64 },
+    /// Convert `amount` of one currency into another, at the rate set with `Engine::set_exchange_rate()`
+    Exchange { from: CurrencyId, to: CurrencyId, amount: f64 },
+    /// Take the trade offered by the last `WorldEffect::Offer` event, if it hasn't run out
+    AcceptOffer,
+}
+
+/// What a successfully applied `EngineCommand` did
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandOutcome {
+    Bought { count: u64, cost: Price },
+    Sold { count: u64, refund: Price },
+    Removed { refund: Price },
+    Upgraded,
+    /// How much the manual action made
+    Performed { quantity: f64 },
+    BuffActivated,
+    Prestiged { level: u32 },
+    TimeScaleSet { previous: f64 },
+    Advanced,
+    Exchanged { received: f64 },
+    Traded { received: Price },
+}
+
+/// The result of a command that was sent to the engine, which arrives once the command has been applied
+#[derive(Debug)]
+pub struct PendingCommand {
+    receiver: Receiver<Result<CommandOutcome>>,
+}
+
+impl PendingCommand {
+    pub(crate) fn new(receiver: Receiver<Result<CommandOutcome>>) -> Self {
+        PendingCommand { receiver }
+    }
+
+    /// Returns the result if the command has been applied, or `None` if it is still queued
+    pub fn try_result(&self) -> Option<Result<CommandOutcome>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Error::CommandDropped)),
+        }
+    }
+
+    /// Blocks until the command has been applied.
+    /// An `EngineMode::SingleThreaded` engine only applies commands from `Engine::update()`,
+    /// so this must not be called from the thread that updates it
+    pub fn wait(self) -> Result<CommandOutcome> {
+        self.receiver.recv().map_err(|_| Error::CommandDropped)?
+    }
+}
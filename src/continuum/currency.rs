@@ -0,0 +1,120 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::continuum::registry::CurrencyId;
+
+/// A currency that producers, products and upgrades can be priced in
+#[derive(Debug, Clone)]
+pub struct Currency {
+    /// The stable key the currency is registered under
+    pub id: String,
+    /// The name shown to the player
+    pub name: String,
+}
+
+/// An amount of the currency registered under the key `currency`.
+/// Definitions refer to currencies by key, as they are written before the currencies are registered
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cost {
+    pub currency: String,
+    pub amount: f64,
+}
+
+impl Cost {
+    pub fn new(currency: &str, amount: f64) -> Self {
+        Cost { currency: currency.to_string(), amount }
+    }
+}
+
+/// Amounts of any number of currencies, by ID. Each currency appears at most once
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Price {
+    amounts: Vec<(CurrencyId, f64)>,
+}
+
+impl Price {
+    /// Adds `amount` of `currency` to the price
+    pub fn add(&mut self, currency: CurrencyId, amount: f64) {
+        match self.amounts.iter_mut().find(|(c, _)| *c == currency) {
+            Some((_, a)) => *a += amount,
+            None => self.amounts.push((currency, amount)),
+        }
+    }
+
+    /// Replaces the amount of `currency` in the price with `amount`
+    pub fn set(&mut self, currency: CurrencyId, amount: f64) {
+        match self.amounts.iter_mut().find(|(c, _)| *c == currency) {
+            Some((_, a)) => *a = amount,
+            None => self.amounts.push((currency, amount)),
+        }
+    }
+
+    /// Adds every amount in `other` to the price
+    pub fn merge(&mut self, other: &Price) {
+        for (currency, amount) in other.iter() {
+            self.add(currency, amount);
+        }
+    }
+
+    pub fn scaled(&self, factor: f64) -> Price {
+        Price { amounts: self.amounts.iter().map(|(c, a)| (*c, a * factor)).collect() }
+    }
+
+    /// How much of `currency` the price includes
+    pub fn amount(&self, currency: CurrencyId) -> f64 {
+        self.amounts.iter().find(|(c, _)| *c == currency).map_or(0.0, |(_, a)| *a)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CurrencyId, f64)> + '_ {
+        self.amounts.iter().copied()
+    }
+}
+
+/// The balance held of every currency
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Wallet {
+    /// Indexed by `CurrencyId`
+    balances: Vec<f64>,
+}
+
+impl Wallet {
+    pub fn balance(&self, currency: CurrencyId) -> f64 {
+        self.balances.get(currency.index()).copied().unwrap_or(0.0)
+    }
+
+    pub fn deposit(&mut self, currency: CurrencyId, amount: f64) {
+        if self.balances.len() <= currency.index() {
+            self.balances.resize(currency.index() + 1, 0.0);
+        }
+        self.balances[currency.index()] += amount;
+    }
+
+    pub fn deposit_all(&mut self, price: &Price) {
+        for (currency, amount) in price.iter() {
+            self.deposit(currency, amount);
+        }
+    }
+
+    /// The first currency in `price` that there isn't enough of, with the amount needed
+    pub fn shortfall(&self, price: &Price) -> Option<(CurrencyId, f64)> {
+        price.iter().find(|(currency, amount)| *amount > self.balance(*currency))
+    }
+
+    /// Takes `price` out of the wallet. Check `shortfall()` first, as balances are allowed to go negative
+    pub fn withdraw(&mut self, price: &Price) {
+        for (currency, amount) in price.iter() {
+            self.deposit(currency, -amount);
+        }
+    }
+}
+
+impl PartialEq for Wallet {
+    /// Wallets are equal if they hold the same balances, whether or not they have seen the same currencies
+    fn eq(&self, other: &Self) -> bool {
+        let len = self.balances.len().max(other.balances.len());
+        (0..len).all(|i| self.balances.get(i).unwrap_or(&0.0) == other.balances.get(i).unwrap_or(&0.0))
+    }
+}
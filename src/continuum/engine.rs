@@ -1,170 +1,1751 @@
-use std::sync::mpsc::{Receiver};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread::{self, JoinHandle};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::continuum::timer::{Timer, TimerState};
-use crate::continuum::entities::{Producer};
+#[cfg(feature = "async")]
+use futures::{channel::mpsc::UnboundedReceiver, Stream, StreamExt};
+
+use crate::continuum::error::{lock, Error, Result};
+use crate::logging::{Level, Target};
+use crate::continuum::timer::Timer;
+use crate::continuum::currency::{Cost, Currency, Price, Wallet};
+use crate::continuum::commands::{CommandOutcome, EngineCommand, PendingCommand};
+use crate::continuum::entities::{ActiveBuff, Buff, BuffEffect, ManualAction, Producer, ProducerEntity, ProductType, Upgrade, UpgradeTarget};
+use crate::continuum::events::{EngineEvent, EventBus};
+use crate::continuum::registry::{ActionId, CurrencyId, ProducerId, ProductId, Registry};
+use crate::continuum::replay::{RecordedCommand, Replay};
+use crate::continuum::rng::Rng;
+use crate::continuum::snapshot::{ActionSnapshot, BalanceSnapshot, ProducerSnapshot, ProductSnapshot, Snapshot};
+use crate::continuum::statistics::Statistics;
+use crate::continuum::storage::ProducerStorage;
+use crate::continuum::world::{ActiveOffer, WorldEffect, WorldEvent};
+
+/// The most units of a producer that can be bought in one go. Each unit is priced separately, so this bounds
+/// the work a single `EngineCommand::Buy` can make for the engine
+pub const MAX_BUY_COUNT: u64 = 10_000;
+
+/// How an `Engine` is driven
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EngineMode {
+    /// A timer thread fires every `tick_timeout_ms` and a second thread processes each tick
+    Threaded,
+    /// No threads are started; the owner calls `Engine::update()` from its own loop, e.g. once per frame
+    SingleThreaded,
+    /// No threads are started; the future returned by `Engine::run()` processes a tick for every item of a stream,
+    /// such as an interval from an async runtime
+    #[cfg(feature = "async")]
+    Async,
+}
 
 /// Defines the configuration for an instance of `Engine`
 #[derive(Debug, Copy, Clone)]
 pub struct EngineConfig {
+    pub mode: EngineMode,
     pub tick_timeout_ms: u64,
+    /// The funds available in the wallet when the engine is created, in the primary currency (the first one added)
+    pub starting_funds: f64,
+    /// The fraction of a producer's purchase cost that is refunded when it is sold
+    pub sell_refund_ratio: f64,
+    /// How much each prestige level adds to the value of everything produced, e.g. 0.1 for +10% per level
+    pub prestige_bonus: f64,
+    /// Seeds the engine's random number generator, so that games started with the same seed play out the same way
+    pub seed: u64,
+    /// The average game time between world events, in ms. 0 turns them off
+    pub world_event_interval_ms: u64,
 }
 
-/// The inner structure of the Engine
-#[derive(Debug)]
-struct EngineInner {
+impl EngineConfig {
+    /// Checks that every setting is in range, so that nothing divides by a zero tick or multiplies by NaN
+    pub fn validate(&self) -> Result<()> {
+        if self.tick_timeout_ms == 0 {
+            return Err(Error::InvalidConfig("tick_timeout_ms must be more than 0"));
+        }
+        if !self.starting_funds.is_finite() || self.starting_funds < 0.0 {
+            return Err(Error::InvalidConfig("starting_funds must be finite and not negative"));
+        }
+        if !(0.0..=1.0).contains(&self.sell_refund_ratio) {
+            return Err(Error::InvalidConfig("sell_refund_ratio must be between 0 and 1"));
+        }
+        if !self.prestige_bonus.is_finite() || self.prestige_bonus < 0.0 {
+            return Err(Error::InvalidConfig("prestige_bonus must be finite and not negative"));
+        }
+        Ok(())
+    }
+}
+
+/// A manual action and how much it has been used
+#[derive(Debug, Clone)]
+struct ActionState {
+    action: ManualAction,
+    product: ProductId,
+    /// Game time left before the action can be used again, in ms
+    cooldown_remaining_ms: u64,
+    uses: u64,
+    produced: f64,
+}
+
+/// The inner structure of the Engine, holding the state of the game.
+/// A clone carries on from the same state independently of the original, without its event subscribers
+#[derive(Debug, Clone)]
+pub(crate) struct EngineInner {
     config: EngineConfig,
-    timer: Arc<Mutex<Timer>>,
-    timer_handle: Option<JoinHandle<()>>,
-    producers: Vec<Arc<Mutex<Box<dyn Producer>>>>,
-    products: HashMap<String, f64>,
+    registry: Registry,
+    producers: ProducerStorage,
+    /// Indexed by `ActionId`
+    actions: Vec<ActionState>,
+    products: HashMap<ProductId, f64>,
+    /// The currency each product's value is paid in, indexed by `ProductId`
+    product_currencies: Vec<CurrencyId>,
+    wallet: Wallet,
+    /// How much of the second currency one unit of the first buys
+    exchange_rates: HashMap<(CurrencyId, CurrencyId), f64>,
+    /// The number of ticks processed so far
+    tick: u64,
+    upgrades: Vec<Upgrade>,
+    /// Buffs with time remaining, in the order they were activated
+    buffs: Vec<ActiveBuff>,
+    /// The events that can happen at random, and the game time until the next one is drawn
+    world_events: Vec<WorldEvent>,
+    next_world_event_ms: Option<u64>,
+    offer: Option<ActiveOffer>,
+    /// The seed `rng` started from, which is saved with replays
+    seed: u64,
+    rng: Rng,
+    prestige: u32,
+    statistics: Statistics,
+    /// How many ms of game time pass for every ms of real time
+    time_scale: f64,
+    /// Game time left over from scaling, carried into the next tick so no time is lost to rounding
+    scaled_carry: f64,
+    /// Time left over from speeding each producer up with buffs, carried over in the same way as `scaled_carry`
+    speed_carry: HashMap<ProducerId, f64>,
+    /// Commands waiting for the next tick, with where to send their results
+    commands: VecDeque<(EngineCommand, Sender<Result<CommandOutcome>>)>,
+    /// Every command applied since recording started, if it has
+    recording: Option<Vec<RecordedCommand>>,
+    events: EventBus,
 }
 
 impl EngineInner {
-    pub fn start_timer(&self) -> Receiver<u64> {
-        self.timer.lock().unwrap().start()
-    }
+    pub fn new(config: EngineConfig) -> Result<Self> {
+        config.validate()?;
 
-    pub fn stop_timer(&self) {
-        self.timer.lock().unwrap().stop()
+        Ok(EngineInner {
+            config,
+            registry: Registry::default(),
+            producers: ProducerStorage::default(),
+            actions: Vec::new(),
+            products: HashMap::new(),
+            product_currencies: Vec::new(),
+            wallet: Wallet::default(),
+            exchange_rates: HashMap::new(),
+            tick: 0,
+            upgrades: Vec::new(),
+            buffs: Vec::new(),
+            world_events: Vec::new(),
+            next_world_event_ms: None,
+            offer: None,
+            seed: config.seed,
+            rng: Rng::new(config.seed),
+            prestige: 0,
+            statistics: Statistics::default(),
+            time_scale: 1.0,
+            scaled_carry: 0.0,
+            speed_carry: HashMap::new(),
+            commands: VecDeque::new(),
+            recording: None,
+            events: EventBus::default(),
+        })
     }
 
-    pub fn timer_state(&self) -> TimerState {
-        self.timer.lock().unwrap().state()
+    pub fn config(&self) -> EngineConfig {
+        self.config
     }
 
     /// This method is called to progress all entities by a tick
     /// A tick is expressed as a unit of elapsed time since the last tick and the `elapsed` value
     /// allows each entity to calculate how much progress it has made since the last tick. 
     pub fn process_tick(&mut self, elapsed: u64) {
-        let iter = { self.producers.iter_mut() };
-        for producer in iter {
-            let mut producer = producer.lock().unwrap();
-            let q = producer.on_tick(elapsed);
-            
-            // if anything was produced, allocate it to the relevant production 'bucket'
-            if q > 0.0 {
-                let product_name = producer.product_type().name.to_string();
-                // println!("{} {} produced...", q, product_name);
-                // now we need to do something with what was produced...
-                let pq = {
-                    match self.products.get(&product_name) {
-                        Some(v) => *v,
-                        None => 0.0,
-                    }
-                };
-                self.products.insert(product_name, pq + q);
-                // println!("{:?}", self.products);
-            }  
-        }           
+        self.apply_commands();
+        let elapsed = self.scale(elapsed);
+        self.run_for(elapsed);
+        self.tick += 1;
     }
 
-    pub fn add_producer(&mut self, producer: Box<dyn Producer>) {
-        self.producers.push(Arc::new(Mutex::new(producer)));
+    /// Progress all entities by an arbitrarily long amount of time, e.g. to account for time spent offline.
+    /// This is how `EngineCommand::Advance` is applied, so it doesn't apply the queued commands itself.
+    ///
+    /// Without converters (producers that consume other products), each producer covers the time in one step
+    /// per span between buff and event changes (see `run_for()`). The cycles completed and the progress left over
+    /// come out exactly as stepping a tick at a time would, but the amounts made are added up in one go, so they can
+    /// differ from the stepped sum by floating point rounding. Converters need to see their inputs arrive as they
+    /// would in play, so if there are any the whole engine is stepped a tick at a time instead, as `process_tick()` does
+    pub fn advance(&mut self, elapsed: u64) {
+        let tick = self.config.tick_timeout_ms;
+
+        if self.producers.any_inputs() {
+            let mut left = elapsed;
+
+            while left > 0 {
+                let step = left.min(tick);
+                let scaled = self.scale(step);
+
+                self.run_for(scaled);
+                if step == tick {
+                    self.tick += 1;
+                }
+                left -= step;
+            }
+            return;
+        }
+
+        self.tick += elapsed / tick;
+
+        let elapsed = self.scale(elapsed);
+        self.run_for(elapsed);
     }
 
-    pub fn get_producer(&self, id: &str) -> Option<Arc<Mutex<Box<dyn Producer>>>> {
-        let mut result = None;
+    /// Produces for `elapsed` ms of game time, counting down the buffs and the offer and drawing world events.
+    /// Buffs only change when one runs out or an event starts one, so the time is split into spans that end where
+    /// a buff, the offer or the wait for the next event does, and each span is produced with the buffs that were
+    /// active throughout it
+    fn run_for(&mut self, elapsed: u64) {
+        let mut remaining = elapsed;
 
-        for p in self.producers.iter() {
-            if p.lock().unwrap().id() == id {
-                result = Some(p.clone());
+        loop {
+            if self.next_world_event_ms.is_none() {
+                self.next_world_event_ms = self.schedule_world_event();
+            }
+            let span = self.buffs.iter().map(|b| b.remaining_ms)
+                .chain(self.offer.as_ref().map(|o| o.remaining_ms))
+                .chain(self.next_world_event_ms)
+                .min()
+                .unwrap_or(remaining)
+                .min(remaining);
+
+            self.produce(span);
+
+            let primary = self.primary_currency();
+            self.statistics.pass_time(span, primary);
+            self.expire_buffs(span);
+            self.expire_offer(span);
+            for state in &mut self.actions {
+                state.cooldown_remaining_ms = state.cooldown_remaining_ms.saturating_sub(span);
+            }
+            if let Some(next) = &mut self.next_world_event_ms {
+                *next -= span;
+                if *next == 0 {
+                    self.next_world_event_ms = None;
+                    self.trigger_world_event();
+                }
+            }
+            remaining -= span;
+            if remaining == 0 {
                 break;
             }
         }
+    }
+
+    /// Counts `elapsed` ms off every buff, removing those that run out.
+    /// A producer no longer sped up by any buff drops its carried time, so the next speed buff starts from nothing
+    fn expire_buffs(&mut self, elapsed: u64) {
+        let events = &mut self.events;
+        let mut slowed = Vec::new();
+
+        self.buffs.retain_mut(|active| {
+            active.remaining_ms = active.remaining_ms.saturating_sub(elapsed);
+            if active.remaining_ms == 0 {
+                log!(Level::Debug, Target::Engine, "buff {} ended", active.buff.id);
+                events.emit(EngineEvent::BuffEnded { name: active.buff.name.clone() });
+                if let BuffEffect::Speed { producer, .. } = active.buff.effect {
+                    slowed.push(producer);
+                }
+            }
+            active.remaining_ms > 0
+        });
+
+        for producer in slowed {
+            let still_sped_up = self.buffs.iter()
+                .any(|b| matches!(b.buff.effect, BuffEffect::Speed { producer: p, .. } if p == producer));
+            if !still_sped_up {
+                self.speed_carry.remove(&producer);
+            }
+        }
+    }
+
+    fn expire_offer(&mut self, elapsed: u64) {
+        if let Some(active) = &mut self.offer {
+            active.remaining_ms = active.remaining_ms.saturating_sub(elapsed);
+            if active.remaining_ms == 0 {
+                self.events.emit(EngineEvent::OfferExpired { name: active.name.clone() });
+                self.offer = None;
+            }
+        }
+    }
+
+    /// How long until the next world event, drawn at random so that they are `world_event_interval_ms` apart
+    /// on average. `None` if there are no events to draw
+    fn schedule_world_event(&mut self) -> Option<u64> {
+        let interval = self.config.world_event_interval_ms;
+
+        if interval == 0 || self.world_events.iter().all(|e| e.weight == 0) {
+            return None;
+        }
+        Some((interval / 2 + self.rng.below(interval + 1)).max(1))
+    }
+
+    /// Draws an event from the weighted table of world events and makes it happen
+    fn trigger_world_event(&mut self) {
+        let total = self.world_events.iter().map(|e| e.weight as u64).sum::<u64>();
+        if total == 0 {
+            return;
+        }
+
+        let mut roll = self.rng.below(total);
+        let event = match self.world_events.iter().find(|e| {
+            let hit = roll < e.weight as u64;
+            roll = roll.saturating_sub(e.weight as u64);
+            hit
+        }) {
+            Some(event) => event.clone(),
+            None => return,
+        };
+
+        log!(Level::Info, Target::Engine, "tick {}: world event {}", self.tick, event.id);
+        self.events.emit(EngineEvent::WorldEvent { name: event.name.clone(), description: event.description.clone() });
+
+        // the keys were checked when the event was added, and products are never unregistered
+        match event.effect {
+            WorldEffect::Boost { product, multiplier, duration_ms } => {
+                if let Some(product) = self.registry.product(&product) {
+                    self.activate_buff(Buff {
+                        id: event.id,
+                        name: event.name,
+                        effect: BuffEffect::Output { product, multiplier },
+                        duration_ms,
+                        cost: Vec::new(),
+                    });
+                }
+            }
+            WorldEffect::Destroy { product, fraction } => {
+                if let Some(product) = self.registry.product(&product) {
+                    let quantity = self.products.entry(product).or_insert(0.0);
+                    let amount = *quantity * fraction.clamp(0.0, 1.0);
+
+                    *quantity -= amount;
+                    self.events.emit(EngineEvent::ProductDestroyed { product, amount });
+                }
+            }
+            WorldEffect::Offer(offer) => {
+                if offer.duration_ms > 0 {
+                    self.offer = Some(ActiveOffer { name: event.name, remaining_ms: offer.duration_ms, offer });
+                }
+            }
+        }
+    }
+
+    /// Ticks every producer and collects what was produced
+    fn produce(&mut self, elapsed: u64) {
+        let prestige_multiplier = self.prestige_multiplier();
+        let products = &mut self.products;
+        let product_currencies = &self.product_currencies;
+        let wallet = &mut self.wallet;
+        let upgrades = &self.upgrades;
+        let buffs = &self.buffs;
+        let statistics = &mut self.statistics;
+        let speed_carry = &mut self.speed_carry;
+
+        // producers sped up by a buff get through more of their cycles in the same time
+        let speed = |id| buffs.iter()
+            .filter_map(|b| match b.buff.effect {
+                BuffEffect::Speed { producer, multiplier } if producer == id => Some(multiplier),
+                _ => None,
+            })
+            .product::<f64>();
+        let elapsed_for = |id| {
+            let speed = speed(id);
+            if speed == 1.0 {
+                return elapsed;
+            }
+
+            let carry = speed_carry.entry(id).or_insert(0.0);
+            let sped_up = elapsed as f64 * speed + *carry;
+            let whole = sped_up.floor();
+
+            *carry = sped_up - whole;
+            whole as u64
+        };
+
+        self.producers.tick(elapsed_for, |id, product, product_type: &ProductType, q| {
+            let q = q * upgrade_multiplier(upgrades, UpgradeTarget::Producer(id)) * output_multiplier(buffs, product);
+
+            // allocate what was produced to the relevant production 'bucket'
+            log!(Level::Trace, Target::Engine, "{} {:?} produced", q, product);
+            let currency = product_currencies[product.index()];
+            let value = q * product_type.value_per_unit * prestige_multiplier;
+
+            *products.entry(product).or_insert(0.0) += q;
+            wallet.deposit(currency, value);
+            statistics.produced(product, q, currency, value);
+        });
+    }
+
+    /// How much the prestige level multiplies the value of everything produced by
+    fn prestige_multiplier(&self) -> f64 {
+        1.0 + self.prestige as f64 * self.config.prestige_bonus
+    }
+
+    /// Converts `elapsed` real time into game time, according to the time scale
+    fn scale(&mut self, elapsed: u64) -> u64 {
+        let scaled = elapsed as f64 * self.time_scale + self.scaled_carry;
+        let whole = scaled.floor();
+
+        self.scaled_carry = scaled - whole;
+        whole as u64
+    }
+
+    /// Queues `command` to be applied at the start of the next tick
+    pub fn queue(&mut self, command: EngineCommand) -> PendingCommand {
+        let (sender, receiver) = channel();
+
+        self.commands.push_back((command, sender));
+        PendingCommand::new(receiver)
+    }
+
+    /// Applies every queued command in the order they were sent
+    fn apply_commands(&mut self) {
+        while let Some((command, sender)) = self.commands.pop_front() {
+            let result = self.apply(command.clone());
+
+            match &result {
+                Ok(outcome) => log!(Level::Info, Target::Engine, "tick {}: {:?} -> {:?}", self.tick, command, outcome),
+                Err(e) => log!(Level::Info, Target::Engine, "tick {}: {:?} failed: {}", self.tick, command, e),
+            }
+            if let Some(recording) = &mut self.recording {
+                recording.push(RecordedCommand { tick: self.tick, command });
+            }
+            // the sender may have stopped waiting for the result, which is fine
+            let _ = sender.send(result);
+        }
+    }
+
+    /// Starts recording every command that is applied, for `replay()`
+    pub fn record(&mut self) {
+        if self.tick > 0 {
+            log!(Level::Warn, Target::Engine, "recording started at tick {}, so it won't play back from a new engine", self.tick);
+        }
+        self.recording.get_or_insert_with(Vec::new);
+    }
+
+    /// The commands recorded so far and the current state, or `None` if `record()` hasn't been called
+    pub fn replay(&self) -> Option<Replay> {
+        self.recording.as_ref().map(|commands| Replay {
+            seed: self.seed,
+            commands: commands.clone(),
+            end_tick: self.tick,
+            end_wallet: self.wallet.clone(),
+        })
+    }
+
+    /// Applies `command` immediately, rather than at the next tick
+    pub fn apply(&mut self, command: EngineCommand) -> Result<CommandOutcome> {
+        match command {
+            EngineCommand::Buy { id, count } => self.buy(id, count).map(|cost| CommandOutcome::Bought { count, cost }),
+            EngineCommand::Sell { id, count } => self.sell(id, count).map(|refund| CommandOutcome::Sold { count, refund }),
+            EngineCommand::Remove(id) => self.remove_producer(id).map(|refund| CommandOutcome::Removed { refund }),
+            EngineCommand::ApplyUpgrade(upgrade) => self.apply_upgrade(upgrade).map(|_| CommandOutcome::Upgraded),
+            EngineCommand::PerformAction(id) => self.perform_action(id).map(|quantity| CommandOutcome::Performed { quantity }),
+            EngineCommand::BuyBuff(buff) => self.buy_buff(buff).map(|_| CommandOutcome::BuffActivated),
+            EngineCommand::Prestige => Ok(CommandOutcome::Prestiged { level: self.prestige() }),
+            EngineCommand::Exchange { from, to, amount } => self.exchange(from, to, amount).map(|received| CommandOutcome::Exchanged { received }),
+            EngineCommand::AcceptOffer => self.accept_offer().map(|received| CommandOutcome::Traded { received }),
+            EngineCommand::Advance { elapsed_ms } => {
+                self.advance(elapsed_ms);
+                Ok(CommandOutcome::Advanced)
+            }
+            EngineCommand::SetTimeScale(scale) => {
+                if !scale.is_finite() || scale < 0.0 {
+                    return Err(Error::InvalidTimeScale(scale));
+                }
+                let previous = self.time_scale;
+                self.time_scale = scale;
+                Ok(CommandOutcome::TimeScaleSet { previous })
+            }
+        }
+    }
+
+    /// Adds a currency, returning its ID. The first currency added is the primary currency,
+    /// which the wallet starts with `EngineConfig::starting_funds` of
+    pub fn add_currency(&mut self, currency: Currency) -> CurrencyId {
+        let first = self.primary_currency().is_none();
+        let id = self.registry.intern_currency(&currency.id, &currency.name);
+
+        if first {
+            self.wallet.deposit(id, self.config.starting_funds);
+        }
+        id
+    }
+
+    /// The first currency added, if any
+    pub fn primary_currency(&self) -> Option<CurrencyId> {
+        self.registry.currency_ids().next()
+    }
+
+    /// Adds a producer, returning its ID. If a producer with the same key was already added, its ID
+    /// is returned and the engine is left unchanged.
+    /// Every currency the producer is priced or paid in must have been added already, and it must take some time
+    /// to make its product
+    pub fn add_producer(&mut self, producer: ProducerEntity) -> Result<ProducerId> {
+        if let Some(id) = self.registry.producer(&producer.id).filter(|id| self.producers.contains(*id)) {
+            return Ok(id);
+        }
+        if producer.production_time_ms == 0 {
+            return Err(Error::ZeroProductionTime(producer.id));
+        }
+        self.price(&producer.base_cost)?;
 
-        result
+        let id = self.registry.intern_producer(&producer.id, &producer.name);
+        let product = self.intern_product(&producer.product_type)?;
+
+        self.producers.insert_entity(id, product, producer);
+        Ok(id)
     }
 
-    pub fn get_products(&self) -> HashMap<String, f64> {
-        self.products.clone()
+    /// Add a producer with its own behaviour, which will be ticked through the `Producer` trait
+    pub fn add_custom_producer(&mut self, producer: Box<dyn Producer>) -> Result<ProducerId> {
+        if let Some(id) = self.registry.producer(producer.id()).filter(|id| self.producers.contains(*id)) {
+            return Ok(id);
+        }
+        if producer.production_time() == 0 {
+            return Err(Error::ZeroProductionTime(producer.id().to_string()));
+        }
+
+        let id = self.registry.intern_producer(producer.id(), producer.name());
+        let product = self.intern_product(producer.product_type())?;
+
+        self.producers.insert_custom(id, product, producer);
+        Ok(id)
     }
+
+    /// Adds a manual action, returning its ID. If an action with the same key was already added, its ID
+    /// is returned and the engine is left unchanged
+    pub fn add_action(&mut self, action: ManualAction) -> Result<ActionId> {
+        if let Some(id) = self.registry.action(&action.id).filter(|id| id.index() < self.actions.len()) {
+            return Ok(id);
+        }
+
+        let product = self.intern_product(&action.product_type)?;
+        let id = self.registry.intern_action(&action.id, &action.name);
+
+        self.actions.push(ActionState { action, product, cooldown_remaining_ms: 0, uses: 0, produced: 0.0 });
+        Ok(id)
+    }
+
+    /// Uses the manual action `id`, adding what it makes to the products and its value to the wallet
+    /// straight away. Returns the quantity made
+    pub fn perform_action(&mut self, id: ActionId) -> Result<f64> {
+        let prestige_multiplier = self.prestige_multiplier();
+        let quantity = self.action_yield(id)?;
+        let state = &mut self.actions[id.index()];
+
+        if state.cooldown_remaining_ms > 0 {
+            return Err(Error::ActionCoolingDown { remaining_ms: state.cooldown_remaining_ms });
+        }
+        state.cooldown_remaining_ms = state.action.cooldown_ms;
+        state.uses += 1;
+        state.produced += quantity;
+
+        let product = state.product;
+        let currency = self.product_currencies[product.index()];
+        let value = quantity * state.action.product_type.value_per_unit * prestige_multiplier;
+
+        *self.products.entry(product).or_insert(0.0) += quantity;
+        self.wallet.deposit(currency, value);
+        self.statistics.produced(product, quantity, currency, value);
+        self.statistics.performed(product, quantity);
+        self.events.emit(EngineEvent::ActionPerformed { id, product, quantity });
+        Ok(quantity)
+    }
+
+    /// How much the next use of the manual action `id` makes, with its upgrades and any buffs to its product
+    fn action_yield(&self, id: ActionId) -> Result<f64> {
+        let state = self.actions.get(id.index()).ok_or(Error::UnknownAction(id))?;
+
+        Ok(state.action.product_type.production_quantity
+            * upgrade_multiplier(&self.upgrades, UpgradeTarget::Action(id))
+            * output_multiplier(&self.buffs, state.product))
+    }
+
+    /// Registers the product, remembering which currency its value is paid in
+    fn intern_product(&mut self, product_type: &ProductType) -> Result<ProductId> {
+        let currency = self.currency(&product_type.currency)?;
+        let product = self.registry.intern_product(&product_type.id, &product_type.name);
+
+        if self.product_currencies.len() <= product.index() {
+            self.product_currencies.push(currency);
+        }
+        Ok(product)
+    }
+
+    fn currency(&self, key: &str) -> Result<CurrencyId> {
+        self.registry.currency(key).ok_or_else(|| Error::UnknownCurrency(key.to_string()))
+    }
+
+    /// Resolves the currency keys in `costs`
+    pub fn price(&self, costs: &[Cost]) -> Result<Price> {
+        let mut price = Price::default();
+
+        for cost in costs {
+            price.add(self.currency(&cost.currency)?, cost.amount);
+        }
+        Ok(price)
+    }
+
+    /// What buying units `from..to` of the producer `id` costs altogether
+    fn price_of_units(&self, id: ProducerId, from: u64, to: u64) -> Result<Price> {
+        let mut price = Price::default();
+
+        for index in from..to {
+            price.merge(&self.price(&self.producers.unit_cost(id, index))?);
+        }
+        Ok(price)
+    }
+
+    /// Takes `price` out of the wallet, unless there isn't enough of one of its currencies
+    fn pay(&mut self, price: &Price) -> Result<()> {
+        if let Some((currency, cost)) = self.wallet.shortfall(price) {
+            return Err(Error::InsufficientFunds {
+                currency: self.registry.currency_key(currency).to_string(),
+                cost,
+                available: self.wallet.balance(currency),
+            });
+        }
+        self.wallet.withdraw(price);
+        Ok(())
+    }
+
+    /// Sets how much of `to` one unit of `from` buys when exchanged, which must be finite and more than 0
+    pub fn set_exchange_rate(&mut self, from: CurrencyId, to: CurrencyId, rate: f64) -> Result<()> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(Error::InvalidExchangeRate(rate));
+        }
+        self.exchange_rates.insert((from, to), rate);
+        Ok(())
+    }
+
+    /// Converts `amount` of `from` into `to` at the exchange rate. Returns the amount of `to` received
+    pub fn exchange(&mut self, from: CurrencyId, to: CurrencyId, amount: f64) -> Result<f64> {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(Error::InvalidAmount(amount));
+        }
+        let rate = self.exchange_rates.get(&(from, to)).copied().ok_or_else(|| Error::NoExchangeRate {
+            from: self.registry.currency_key(from).to_string(),
+            to: self.registry.currency_key(to).to_string(),
+        })?;
+
+        let mut price = Price::default();
+        price.add(from, amount);
+        self.pay(&price)?;
+
+        let received = amount * rate;
+        self.wallet.deposit(to, received);
+        self.events.emit(EngineEvent::Exchanged { from, to, amount, received });
+        Ok(received)
+    }
+
+    /// Restarts the random number generator from `seed`, e.g. to play back a replay of a game with that seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+        self.next_world_event_ms = None;
+    }
+
+    /// Adds `event` to the table that world events are drawn from.
+    /// Every product and currency the event refers to must have been added already
+    pub fn add_world_event(&mut self, event: WorldEvent) -> Result<()> {
+        match &event.effect {
+            WorldEffect::Boost { product, .. } | WorldEffect::Destroy { product, .. } => {
+                self.registry.product(product).ok_or_else(|| Error::UnknownProduct(product.clone()))?;
+            }
+            WorldEffect::Offer(offer) => {
+                self.price(&offer.give)?;
+                self.price(&offer.receive)?;
+            }
+        }
+
+        self.world_events.push(event);
+        Ok(())
+    }
+
+    /// Takes the offer on the table, paying what it asks for. Returns what was received
+    pub fn accept_offer(&mut self) -> Result<Price> {
+        let active = self.offer.as_ref().ok_or(Error::NoOffer)?;
+        let paid = self.price(&active.offer.give)?;
+        let received = self.price(&active.offer.receive)?;
+
+        self.pay(&paid)?;
+        self.wallet.deposit_all(&received);
+        if let Some(active) = self.offer.take() {
+            self.events.emit(EngineEvent::OfferAccepted { name: active.name, paid, received: received.clone() });
+        }
+        Ok(received)
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn producers(&self) -> &ProducerStorage {
+        &self.producers
+    }
+
+    /// The currency that the value of the product `id` is paid in
+    pub fn product_currency(&self, id: ProductId) -> CurrencyId {
+        self.product_currencies[id.index()]
+    }
+
+    pub fn wallet(&self) -> &Wallet {
+        &self.wallet
+    }
+
+    /// Buys `count` units of the producer `id` if all of them can be paid for. Returns the total cost
+    pub fn buy(&mut self, id: ProducerId, count: u64) -> Result<Price> {
+        if !self.producers.contains(id) {
+            return Err(Error::UnknownProducer(id));
+        }
+        let owned = self.producers.count(id);
+        let total = owned.checked_add(count)
+            .filter(|_| count <= MAX_BUY_COUNT)
+            .ok_or(Error::TooManyUnits { owned, requested: count })?;
+        let cost = self.price_of_units(id, owned, total)?;
+
+        self.pay(&cost)?;
+        self.producers.set_count(id, total);
+        self.statistics.bought(count);
+        self.statistics.spent(&cost);
+        for index in owned..total {
+            let cost = self.price_of_units(id, index, index + 1)?;
+            self.events.emit(EngineEvent::Purchased { id, count: index + 1, cost });
+        }
+        Ok(cost)
+    }
+
+    /// Pays for `upgrade` and starts applying its multiplier to the output of its target
+    pub fn apply_upgrade(&mut self, upgrade: Upgrade) -> Result<()> {
+        match upgrade.target {
+            UpgradeTarget::Producer(id) if !self.producers.contains(id) => return Err(Error::UnknownProducer(id)),
+            UpgradeTarget::Action(id) if id.index() >= self.actions.len() => return Err(Error::UnknownAction(id)),
+            _ => (),
+        }
+        if self.upgrades.iter().any(|u| u.id == upgrade.id) {
+            return Err(Error::AlreadyUpgraded(upgrade.id));
+        }
+        let cost = self.price(&upgrade.cost)?;
+
+        self.pay(&cost)?;
+        self.statistics.spent(&cost);
+        self.events.emit(EngineEvent::Upgraded {
+            target: upgrade.target,
+            name: upgrade.name.clone(),
+            multiplier: upgrade.multiplier,
+        });
+        self.upgrades.push(upgrade);
+        Ok(())
+    }
+
+    /// Pays for `buff` and activates it
+    pub fn buy_buff(&mut self, buff: Buff) -> Result<()> {
+        let cost = self.price(&buff.cost)?;
+
+        self.pay(&cost)?;
+        self.statistics.spent(&cost);
+        self.activate_buff(buff);
+        Ok(())
+    }
+
+    /// Starts `buff` without paying for it, e.g. as the reward for an event.
+    /// If it is already active its remaining time is topped back up to its full duration
+    pub fn activate_buff(&mut self, buff: Buff) {
+        log!(Level::Debug, Target::Engine, "buff {} started for {}ms", buff.id, buff.duration_ms);
+        self.events.emit(EngineEvent::BuffStarted { name: buff.name.clone(), duration_ms: buff.duration_ms });
+
+        match self.buffs.iter_mut().find(|b| b.buff.id == buff.id) {
+            Some(active) => active.remaining_ms = active.remaining_ms.max(buff.duration_ms),
+            None if buff.duration_ms > 0 => self.buffs.push(ActiveBuff { remaining_ms: buff.duration_ms, buff }),
+            None => (),
+        }
+    }
+
+    /// Resets every producer, product, upgrade, buff and the wallet, raising the prestige level by one.
+    /// Returns the new level
+    pub fn prestige(&mut self) -> u32 {
+        let ids = self.producers.ids().collect::<Vec<_>>();
+        for id in ids {
+            self.producers.set_count(id, 0);
+            self.producers.set_time_elapsed(id, 0);
+        }
+        for state in &mut self.actions {
+            state.cooldown_remaining_ms = 0;
+        }
+        self.products.clear();
+        self.upgrades.clear();
+        self.buffs.clear();
+        self.speed_carry.clear();
+        self.offer = None;
+        self.wallet = Wallet::default();
+        if let Some(primary) = self.primary_currency() {
+            self.wallet.deposit(primary, self.config.starting_funds);
+        }
+        self.prestige += 1;
+        self.statistics.start_run();
+
+        self.events.emit(EngineEvent::Prestiged { level: self.prestige });
+        self.prestige
+    }
+
+    /// What selling the most recently bought `count` units of the producer `id` would refund
+    pub fn refund(&self, id: ProducerId, count: u64) -> Result<Price> {
+        if !self.producers.contains(id) {
+            return Err(Error::UnknownProducer(id));
+        }
+        let owned = self.producers.count(id);
+        let paid = self.price_of_units(id, owned - count.min(owned), owned)?;
+
+        Ok(paid.scaled(self.config.sell_refund_ratio))
+    }
+
+    /// Sells `count` units of the producer `id` back for a fraction of what they cost.
+    /// The units that remain keep the progress made on the current cycle, but if none remain that progress is lost.
+    /// Returns the refund
+    pub fn sell(&mut self, id: ProducerId, count: u64) -> Result<Price> {
+        if !self.producers.contains(id) {
+            return Err(Error::UnknownProducer(id));
+        }
+        let owned = self.producers.count(id);
+
+        if count == 0 || owned < count {
+            return Err(Error::NotEnoughOwned { owned, requested: count });
+        }
+
+        let refund = self.refund(id, count)?;
+        let remaining = owned - count;
+
+        self.producers.set_count(id, remaining);
+        if remaining == 0 {
+            self.producers.set_time_elapsed(id, 0);
+        }
+        self.wallet.deposit_all(&refund);
+        self.events.emit(EngineEvent::Sold { id, count, refund: refund.clone() });
+        Ok(refund)
+    }
+
+    /// Removes the producer `id` from the engine, selling back every unit that is owned.
+    /// Any progress made on the current cycle is lost. Returns the refund
+    pub fn remove_producer(&mut self, id: ProducerId) -> Result<Price> {
+        if !self.producers.contains(id) {
+            return Err(Error::UnknownProducer(id));
+        }
+
+        let refund = self.refund(id, self.producers.count(id))?;
+
+        self.producers.remove(id);
+        self.wallet.deposit_all(&refund);
+        self.events.emit(EngineEvent::Removed { id, refund: refund.clone() });
+        Ok(refund)
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<EngineEvent> {
+        self.events.subscribe()
+    }
+
+    /// Carries the lifetime statistics of a previous game, e.g. loaded with `Statistics::load()`, into this one
+    pub fn restore_statistics(&mut self, saved: Statistics) {
+        self.statistics.restore(saved);
+    }
+
+    #[cfg(feature = "async")]
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut products = self.products.iter().map(|(&id, &quantity)| ProductSnapshot {
+            id,
+            key: self.registry.product_key(id).to_string(),
+            name: self.registry.product_name(id).to_string(),
+            quantity,
+        }).collect::<Vec<_>>();
+        products.sort_by_key(|p| p.id);
+
+        let mut producers = self.producers.ids().map(|id| ProducerSnapshot {
+            id,
+            key: self.registry.producer_key(id).to_string(),
+            name: self.registry.producer_name(id).to_string(),
+            product: self.producers.product(id),
+            count: self.producers.count(id),
+            // a custom producer priced in an unknown currency can't be bought, which is reported when trying to
+            cost: self.price(&self.producers.cost(id)).unwrap_or_default(),
+            production_time_ms: self.producers.production_time(id),
+            time_elapsed_ms: self.producers.time_elapsed(id),
+        }).collect::<Vec<_>>();
+        producers.sort_by_key(|p| p.id);
+
+        let actions = self.registry.action_ids().zip(&self.actions).map(|(id, state)| ActionSnapshot {
+            id,
+            key: self.registry.action_key(id).to_string(),
+            name: self.registry.action_name(id).to_string(),
+            product: state.product,
+            next_yield: self.action_yield(id).unwrap_or_default(),
+            cooldown_ms: state.action.cooldown_ms,
+            cooldown_remaining_ms: state.cooldown_remaining_ms,
+            uses: state.uses,
+            produced: state.produced,
+        }).collect();
+
+        let wallet = self.registry.currency_ids().map(|id| BalanceSnapshot {
+            id,
+            key: self.registry.currency_key(id).to_string(),
+            name: self.registry.currency_name(id).to_string(),
+            balance: self.wallet.balance(id),
+        }).collect();
+
+        Snapshot {
+            tick: self.tick,
+            wallet,
+            products,
+            producers,
+            actions,
+            upgrades: self.upgrades.clone(),
+            buffs: self.buffs.clone(),
+            offer: self.offer.clone(),
+            statistics: self.statistics.clone(),
+            prestige: self.prestige,
+            time_scale: self.time_scale,
+        }
+    }
+}
+
+/// What the upgrades applied to `target` multiply its output by
+fn upgrade_multiplier(upgrades: &[Upgrade], target: UpgradeTarget) -> f64 {
+    upgrades.iter().filter(|u| u.target == target).map(|u| u.multiplier).product()
+}
+
+/// What the active buffs multiply the amount of `product` made by
+fn output_multiplier(buffs: &[ActiveBuff], product: ProductId) -> f64 {
+    buffs.iter()
+        .filter_map(|b| match b.buff.effect {
+            BuffEffect::Output { product: p, multiplier } if p == product => Some(multiplier),
+            _ => None,
+        })
+        .product()
+}
+
+/// How the state of an `Engine` is held and moved forward, depending on its `EngineMode`
+#[derive(Debug)]
+enum Driver {
+    Threaded {
+        inner: Arc<Mutex<EngineInner>>,
+        timer: Timer,
+        tick_thread: Option<JoinHandle<Result<()>>>,
+    },
+    SingleThreaded {
+        inner: Box<EngineInner>,
+        running: bool,
+        last_update: Option<Instant>,
+        /// Time that has passed since the last whole tick was processed
+        pending: Duration,
+    },
+    #[cfg(feature = "async")]
+    Async {
+        inner: Arc<Mutex<EngineInner>>,
+        running: Arc<AtomicBool>,
+    },
 }
 
 #[derive(Debug)]
 pub struct Engine {
-    inner: Arc<Mutex<EngineInner>>,
+    driver: Driver,
 }
 
 impl Engine {
-    pub fn new(config: EngineConfig) -> Self {
-        Engine {
-            inner: Arc::new(Mutex::new(EngineInner {
-                config,
-                timer: Arc::new(Mutex::new(Timer::new(config.tick_timeout_ms))),
-                timer_handle: None,
-                producers: Vec::new(),
-                products: HashMap::new(),
-            })),
-        }
-    }
-
-    pub fn start(&self) {
-        let local_self = self.inner.clone();
-        let receiver = local_self.lock().unwrap().start_timer();
-
-        self.inner.lock().unwrap().timer_handle = Some(
-            thread::spawn(move || {
-                loop {
-                    let state = { local_self.lock().unwrap().timer_state() };
-
-                    match state {
-                        TimerState::Running => {
-                            match receiver.recv() {
-                                Ok(elapsed) => {
-                                    // println!("Tick received {}", elapsed);
-                                    local_self.lock().unwrap().process_tick(elapsed);
-                                },
-                                Err(e) => {
-                                    println!("Recv error: {}", e);
-                                    break;
-                                },
-                            }
-                        },
-                        TimerState::Stopped => break,
+    /// Creates an engine that hasn't been started, or fails if `config` doesn't pass `EngineConfig::validate()`
+    pub fn new(config: EngineConfig) -> Result<Self> {
+        let inner = EngineInner::new(config)?;
+        let driver = match config.mode {
+            EngineMode::Threaded => Driver::Threaded {
+                inner: Arc::new(Mutex::new(inner)),
+                timer: Timer::new(config.tick_timeout_ms),
+                tick_thread: None,
+            },
+            EngineMode::SingleThreaded => Driver::SingleThreaded {
+                inner: Box::new(inner),
+                running: false,
+                last_update: None,
+                pending: Duration::ZERO,
+            },
+            #[cfg(feature = "async")]
+            EngineMode::Async => Driver::Async {
+                inner: Arc::new(Mutex::new(inner)),
+                running: Arc::new(AtomicBool::new(false)),
+            },
+        };
+
+        Ok(Engine { driver })
+    }
+
+    /// Starts the engine. In `EngineMode::Threaded` this starts the timer, and a thread that processes a tick
+    /// of the engine each time the timer fires. In `EngineMode::SingleThreaded` ticks are only processed by `update()`.
+    /// Starting an engine that is already running does nothing, and an engine can be started again after `stop()`
+    pub fn start(&mut self) -> Result<()> {
+        match &mut self.driver {
+            Driver::Threaded { inner, timer, tick_thread } => {
+                match tick_thread.as_ref().map(|h| h.is_finished()) {
+                    Some(false) => return Ok(()),
+                    // the tick thread ended by itself after an error, which is reported here rather than lost
+                    Some(true) => Self::stop_threads(timer, tick_thread)?,
+                    None => (),
+                }
+
+                let receiver = timer.start()?;
+                let local_inner = inner.clone();
+
+                *tick_thread = Some(thread::spawn(move || {
+                    // the loop ends once the timer stops and drops its end of the channel
+                    for elapsed in receiver {
+                        log!(Level::Trace, Target::Engine, "tick received: {}ms", elapsed);
+                        lock(&local_inner, "engine")?.process_tick(elapsed);
                     }
+                    log!(Level::Debug, Target::Engine, "timer channel closed");
+                    Ok(())
+                }));
+            }
+            Driver::SingleThreaded { running, last_update, .. } => {
+                if *running {
+                    return Ok(());
                 }
-                println!("Engine's time recv thread terminating");
-            })
-        );
+                *running = true;
+                *last_update = None;
+            }
+            #[cfg(feature = "async")]
+            Driver::Async { running, .. } => {
+                if running.swap(true, Ordering::SeqCst) {
+                    return Ok(());
+                }
+            }
+        }
+
+        log!(Level::Info, Target::Engine, "engine started");
+        Ok(())
     }
 
-    pub fn stop(&mut self) {
-        let handle = {
-            let mut inner = self.inner.lock().unwrap();
+    /// Stops the engine. In `EngineMode::Threaded` this waits for the engine's tick thread to finish and reports
+    /// any error that stopped it early. Stopping an engine that isn't running does nothing
+    pub fn stop(&mut self) -> Result<()> {
+        match &mut self.driver {
+            Driver::Threaded { timer, tick_thread, .. } => {
+                if tick_thread.is_none() {
+                    return Ok(());
+                }
+                Self::stop_threads(timer, tick_thread)?;
+            }
+            Driver::SingleThreaded { running, .. } => {
+                if !*running {
+                    return Ok(());
+                }
+                *running = false;
+            }
+            #[cfg(feature = "async")]
+            Driver::Async { running, .. } => {
+                if !running.swap(false, Ordering::SeqCst) {
+                    return Ok(());
+                }
+            }
+        }
+
+        log!(Level::Info, Target::Engine, "engine stopped");
+        Ok(())
+    }
+
+    fn stop_threads(timer: &mut Timer, tick_thread: &mut Option<JoinHandle<Result<()>>>) -> Result<()> {
+        let timer_result = timer.stop();
+
+        if let Some(handle) = tick_thread.take() {
+            handle.join().map_err(|_| Error::ThreadPanicked("engine"))??;
+        }
+        timer_result
+    }
+
+    /// Moves a running `EngineMode::SingleThreaded` engine forward to `now`, processing every whole tick that
+    /// has passed since the last update. Time left over is carried into the next update, so the engine
+    /// progresses in the same steps however often this is called, and a `Replay` of it plays back exactly.
+    /// Does nothing in `EngineMode::Threaded`
+    pub fn update(&mut self, now: Instant) -> Result<()> {
+        if let Driver::SingleThreaded { inner, running: true, last_update, pending } = &mut self.driver {
+            if let Some(last) = last_update {
+                *pending += now.saturating_duration_since(*last);
+            }
+            *last_update = Some(now);
+
+            let tick_ms = inner.config().tick_timeout_ms;
+            let tick = Duration::from_millis(tick_ms);
 
-            if inner.timer_state() == TimerState::Running {
-                inner.stop_timer();
+            // ticks are processed one at a time, rather than with `advance()`, so that commands are applied
+            // at the same tick and floating point results are summed in the same order as in a replay
+            while *pending >= tick {
+                *pending -= tick;
+                inner.process_tick(tick_ms);
             }
-    
-            inner.timer_handle.take()
+        }
+        Ok(())
+    }
+
+    /// Returns a future that drives an `EngineMode::Async` engine, processing a tick each time `ticks` yields an item,
+    /// e.g. from an interval provided by the async runtime. The engine is started when the future is created,
+    /// and the future completes once `stop()` is called or `ticks` ends. Subscribers to `snapshots()` are sent a
+    /// `Snapshot` after every tick. Engines in other modes aren't started, and `Error::WrongMode` is returned
+    #[cfg(feature = "async")]
+    pub fn run<S>(&mut self, ticks: S) -> Result<impl Future<Output = Result<()>> + Send + 'static>
+    where
+        S: Stream + Send + Unpin + 'static,
+    {
+        let (inner, running) = match &self.driver {
+            Driver::Async { inner, running } => (inner.clone(), running.clone()),
+            _ => return Err(Error::WrongMode),
         };
+        self.start()?;
+
+        Ok(async move {
+            let mut ticks = ticks;
+            let mut last_tick = Instant::now();
+            // time short of a whole ms, carried into the next tick so that none is lost
+            let mut carry = Duration::ZERO;
 
-        match handle {
-            Some(h) => {
-                match h.join() {
-                    Err(e) => println!("JOIN Error: {:?}", e),
-                    Ok(_) => println!("Engine timer thread joined"),
+            while ticks.next().await.is_some() {
+                if !running.load(Ordering::SeqCst) {
+                    break;
                 }
-            },
-            None => println!("Engine.stop() called but not timer thread exists"),
+
+                let now = Instant::now();
+                carry += now.duration_since(last_tick);
+                last_tick = now;
+                let elapsed = carry.as_millis() as u64;
+                carry -= Duration::from_millis(elapsed);
+
+                log!(Level::Trace, Target::Engine, "tick received: {}ms", elapsed);
+                let mut inner = lock(&inner, "engine")?;
+                inner.process_tick(elapsed);
+                if inner.events_mut().has_snapshot_subscribers() {
+                    let snapshot = inner.snapshot();
+                    inner.events_mut().publish_snapshot(snapshot);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Like `subscribe()`, but events arrive on a `Stream`
+    #[cfg(feature = "async")]
+    pub fn subscribe_stream(&mut self) -> Result<UnboundedReceiver<EngineEvent>> {
+        self.write(|inner| inner.events_mut().subscribe_stream())
+    }
+
+    /// Returns a `Stream` of the engine's state, sent after every tick processed by `run()`
+    #[cfg(feature = "async")]
+    pub fn snapshots(&mut self) -> Result<UnboundedReceiver<Snapshot>> {
+        self.write(|inner| inner.events_mut().subscribe_snapshots())
+    }
+
+    /// Runs `f` with shared access to the engine's state
+    fn read<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&EngineInner) -> R,
+    {
+        match &self.driver {
+            Driver::Threaded { inner, .. } => Ok(f(&*lock(inner, "engine")?)),
+            #[cfg(feature = "async")]
+            Driver::Async { inner, .. } => Ok(f(&*lock(inner, "engine")?)),
+            Driver::SingleThreaded { inner, .. } => Ok(f(inner)),
         }
     }
 
+    /// Runs `f` with exclusive access to the engine's state
+    fn write<R, F>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut EngineInner) -> R,
+    {
+        match &mut self.driver {
+            Driver::Threaded { inner, .. } => Ok(f(&mut *lock(inner, "engine")?)),
+            #[cfg(feature = "async")]
+            Driver::Async { inner, .. } => Ok(f(&mut *lock(inner, "engine")?)),
+            Driver::SingleThreaded { inner, .. } => Ok(f(inner)),
+        }
+    }
+
+    /// Add a currency to the engine. Currencies must be added before the producers that are priced or paid in them.
+    /// The first currency added is the primary currency, which the wallet starts with `EngineConfig::starting_funds` of
+    pub fn add_currency(&mut self, currency: Currency) -> Result<CurrencyId> {
+        self.write(|inner| inner.add_currency(currency))
+    }
+
+    /// Look up the ID of the currency registered under `key`
+    pub fn currency_id(&self, key: &str) -> Result<Option<CurrencyId>> {
+        self.read(|inner| inner.registry().currency(key))
+    }
+
+    /// Let `EngineCommand::Exchange` convert `from` into `to`, with one unit of `from` buying `rate` units of `to`.
+    /// Like adding currencies and producers, this sets the game up rather than playing it, so it takes effect
+    /// straight away instead of being queued. A replay is played against an engine that has been set up the same way
+    pub fn set_exchange_rate(&mut self, from: CurrencyId, to: CurrencyId, rate: f64) -> Result<()> {
+        self.write(|inner| inner.set_exchange_rate(from, to, rate))?
+    }
+
     /// Add a producer to the engine.
-    /// Once a producer has been added to the engine and the timer is in a state of `TimerState::Running`,
-    /// the producer will receive calls to its `on_tick()` method for processing
-    pub fn add_producer(&mut self, producer: Box<dyn Producer>) {
-        self.inner.lock().unwrap().add_producer(producer);
+    /// Once a producer has been added to the engine and the engine is running,
+    /// the producer will make progress on every tick
+    pub fn add_producer(&mut self, producer: ProducerEntity) -> Result<ProducerId> {
+        self.write(|inner| inner.add_producer(producer))?
+    }
+
+    /// Add a producer that implements its own behaviour.
+    /// The producer will receive calls to its `on_tick()` method for processing
+    pub fn add_custom_producer(&mut self, producer: Box<dyn Producer>) -> Result<ProducerId> {
+        self.write(|inner| inner.add_custom_producer(producer))?
+    }
+
+    /// Add an event to the table that world events are drawn from, roughly every `EngineConfig::world_event_interval_ms`.
+    /// Every product and currency the event refers to must have been added already
+    pub fn add_world_event(&mut self, event: WorldEvent) -> Result<()> {
+        self.write(|inner| inner.add_world_event(event))?
+    }
+
+    /// Add a manual action that the player can use with `EngineCommand::PerformAction`
+    pub fn add_action(&mut self, action: ManualAction) -> Result<ActionId> {
+        self.write(|inner| inner.add_action(action))?
+    }
+
+    /// Look up the ID of the manual action registered under `key`
+    pub fn action_id(&self, key: &str) -> Result<Option<ActionId>> {
+        self.read(|inner| inner.registry().action(key))
+    }
+
+    /// Look up the ID of the producer registered under `key`
+    pub fn producer_id(&self, key: &str) -> Result<Option<ProducerId>> {
+        self.read(|inner| inner.registry().producer(key))
+    }
+
+    /// Look up the ID of the product registered under `key`
+    pub fn product_id(&self, key: &str) -> Result<Option<ProductId>> {
+        self.read(|inner| inner.registry().product(key))
+    }
+
+    /// A copy of the registry, for mapping IDs to display names
+    pub fn registry(&self) -> Result<Registry> {
+        self.read(|inner| inner.registry().clone())
+    }
+
+    /// A consistent copy of the whole game state, taken in one go.
+    /// Prefer this over several calls to the individual getters when the values need to agree, e.g. when rendering a frame
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        self.read(|inner| inner.snapshot())
+    }
+
+    /// Fast-forward the engine by `duration`, e.g. to grant progress made while the game wasn't running.
+    /// This gives the same result as letting the timer tick for that long without the wait, give or take floating
+    /// point rounding in the amounts made.
+    /// Queued as `EngineCommand::Advance`, so it happens after the commands sent before it and is recorded in replays
+    pub fn advance(&mut self, duration: Duration) -> Result<PendingCommand> {
+        self.send(EngineCommand::Advance { elapsed_ms: duration.as_millis() as u64 })
+    }
+
+    /// A copy of the balance of every currency
+    pub fn wallet(&self) -> Result<Wallet> {
+        self.read(|inner| inner.wallet().clone())
+    }
+
+    /// Sell `count` units of the producer `id` for `EngineConfig::sell_refund_ratio` of what they cost.
+    /// Queued as `EngineCommand::Sell`, so the refund arrives on the returned `PendingCommand`
+    pub fn sell_producer(&mut self, id: ProducerId, count: u64) -> Result<PendingCommand> {
+        self.send(EngineCommand::Sell { id, count })
+    }
+
+    /// Remove the producer `id` from the engine, refunding every unit that is owned.
+    /// Queued as `EngineCommand::Remove`, so the refund arrives on the returned `PendingCommand`
+    pub fn remove_producer(&mut self, id: ProducerId) -> Result<PendingCommand> {
+        self.send(EngineCommand::Remove(id))
+    }
+
+    /// Carry the lifetime statistics of a previous game into this one. The current run's statistics are kept
+    pub fn restore_statistics(&mut self, saved: Statistics) -> Result<()> {
+        self.write(|inner| inner.restore_statistics(saved))
+    }
+
+    /// Returns a receiver that is sent an `EngineEvent` whenever producers are bought, sold or removed
+    pub fn subscribe(&mut self) -> Result<Receiver<EngineEvent>> {
+        self.write(|inner| inner.subscribe())
+    }
+
+    /// Start recording the commands applied to the engine, so they can be saved with `replay()`.
+    /// For the replay to play back from a new engine, this should be called before the engine is started
+    pub fn record(&mut self) -> Result<()> {
+        self.write(|inner| inner.record())
+    }
+
+    /// The commands recorded since `record()` was called, and the engine's current tick and wallet
+    pub fn replay(&self) -> Result<Option<Replay>> {
+        self.read(|inner| inner.replay())
+    }
+
+    /// Queue `command` to be applied at the start of the next tick. Commands are applied in the order they are sent,
+    /// and the returned `PendingCommand` receives the result once the command has been applied
+    pub fn send(&mut self, command: EngineCommand) -> Result<PendingCommand> {
+        self.write(|inner| inner.queue(command))
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        if let Err(e) = self.stop() {
+            log!(Level::Warn, Target::Engine, "failed to stop engine while dropping it: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content;
+
+    const TICK_MS: u64 = 50;
+
+    fn config() -> EngineConfig {
+        EngineConfig {
+            mode: EngineMode::SingleThreaded,
+            tick_timeout_ms: TICK_MS,
+            starting_funds: 0.0,
+            sell_refund_ratio: 0.5,
+            prestige_bonus: 0.1,
+            seed: 1,
+            world_event_interval_ms: 0,
+        }
+    }
+
+    fn producer(id: &str, product: &str, production_time_ms: u64, count: u64) -> ProducerEntity {
+        ProducerEntity {
+            id: id.to_string(),
+            name: id.to_string(),
+            base_cost: vec![Cost::new("coins", 1.0)],
+            cost_coefficient: 1.1,
+            product_type: ProductType {
+                id: product.to_string(),
+                name: product.to_string(),
+                production_quantity: 0.1,
+                value_per_unit: 0.3,
+                currency: "coins".to_string(),
+            },
+            production_time_ms,
+            time_elapsed: 0,
+            count,
+        }
+    }
+
+    /// A producer that consumes other products, which `advance()` can't fast-forward
+    #[derive(Debug)]
+    struct Converter(ProducerEntity);
+
+    impl Producer for Converter {
+        fn id(&self) -> &str { self.0.id() }
+        fn name(&self) -> &str { self.0.name() }
+        fn production_time(&self) -> u64 { self.0.production_time() }
+        fn set_production_time(&mut self, t: u64) { self.0.set_production_time(t) }
+        fn production_quantity(&self) -> f64 { self.0.production_quantity() }
+        fn set_production_quantity(&mut self, quantity: f64) { self.0.set_production_quantity(quantity) }
+        fn product_type(&self) -> &ProductType { self.0.product_type() }
+        fn count(&self) -> u64 { self.0.count() }
+        fn set_count(&mut self, count: u64) { self.0.set_count(count) }
+        fn time_elapsed(&self) -> u64 { self.0.time_elapsed() }
+        fn set_time_elapsed(&mut self, t: u64) { self.0.set_time_elapsed(t) }
+        fn unit_cost(&self, index: u64) -> Vec<Cost> { self.0.unit_cost(index) }
+        fn has_inputs(&self) -> bool { true }
+        fn on_tick(&mut self, delta: u64) -> f64 { self.0.on_tick(delta) }
+        fn clone_box(&self) -> Box<dyn Producer> { Box::new(Converter(self.0.clone())) }
+    }
+
+    fn engine(converters: bool) -> EngineInner {
+        let mut inner = EngineInner::new(config()).unwrap();
+        inner.add_currency(Currency { id: "coins".to_string(), name: "Coins".to_string() });
+        inner.add_producer(producer("miner", "gold", 300, 3)).unwrap();
+        inner.add_producer(producer("cutter", "wood", 1_700, 2)).unwrap();
+        if converters {
+            inner.add_custom_producer(Box::new(Converter(producer("smelter", "bars", 450, 1)))).unwrap();
+            inner.add_custom_producer(Box::new(Converter(producer("smith", "tools", 2_000, 1)))).unwrap();
+        }
+        inner
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn a_zero_tick_is_rejected() {
+        let config = EngineConfig { tick_timeout_ms: 0, ..config() };
+
+        assert!(matches!(Engine::new(config), Err(Error::InvalidConfig(_))));
+        assert!(matches!(EngineInner::new(config), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn advance_matches_stepping_without_converters() {
+        let (mut advanced, mut stepped) = (engine(false), engine(false));
+
+        advanced.advance(200 * TICK_MS);
+        for _ in 0..200 {
+            stepped.process_tick(TICK_MS);
+        }
+
+        let (advanced, stepped) = (advanced.snapshot(), stepped.snapshot());
+        assert_eq!(advanced.tick, stepped.tick);
+        for (a, s) in advanced.producers.iter().zip(&stepped.producers) {
+            assert_eq!((a.count, a.time_elapsed_ms), (s.count, s.time_elapsed_ms));
+        }
+        for (a, s) in advanced.products.iter().zip(&stepped.products) {
+            assert_eq!(a.id, s.id);
+            assert_close(a.quantity, s.quantity);
+        }
+        for (a, s) in advanced.wallet.iter().zip(&stepped.wallet) {
+            assert_close(a.balance, s.balance);
+        }
+    }
+
+    #[test]
+    fn advance_steps_converter_chains_like_process_tick() {
+        let (mut advanced, mut stepped) = (engine(true), engine(true));
+
+        advanced.advance(200 * TICK_MS);
+        for _ in 0..200 {
+            stepped.process_tick(TICK_MS);
+        }
+
+        assert_eq!(advanced.snapshot(), stepped.snapshot());
+    }
+
+    #[test]
+    fn producers_without_a_production_time_are_rejected() {
+        let mut inner = engine(false);
+
+        assert_eq!(inner.add_producer(producer("instant", "gold", 0, 1)), Err(Error::ZeroProductionTime("instant".to_string())));
+        assert_eq!(
+            inner.add_custom_producer(Box::new(Converter(producer("forge", "bars", 0, 1)))),
+            Err(Error::ZeroProductionTime("forge".to_string())),
+        );
+        assert!(inner.registry().producer("instant").is_none());
+
+        // a custom producer can still have its production time set to 0 once it has been added
+        let mut time_elapsed = 20;
+        assert_eq!(crate::continuum::entities::run_cycles(&mut time_elapsed, 50, 0), 0);
+        assert_eq!(time_elapsed, 20);
+    }
+
+    #[test]
+    fn buying_too_many_at_once_is_rejected() {
+        let mut inner = engine(false);
+        let miner = inner.registry().producer("miner").unwrap();
+
+        assert_eq!(inner.buy(miner, MAX_BUY_COUNT + 1), Err(Error::TooManyUnits { owned: 3, requested: MAX_BUY_COUNT + 1 }));
+        assert_eq!(inner.buy(miner, u64::MAX), Err(Error::TooManyUnits { owned: 3, requested: u64::MAX }));
+        assert_eq!(inner.producers().count(miner), 3);
+    }
+
+    #[test]
+    fn selling_keeps_the_progress_of_the_units_left() {
+        let mut inner = engine(false);
+        let miner = inner.registry().producer("miner").unwrap();
+
+        inner.process_tick(TICK_MS);
+        inner.process_tick(TICK_MS);
+        assert_eq!(inner.producers().time_elapsed(miner), 2 * TICK_MS);
+
+        inner.sell(miner, 1).unwrap();
+        assert_eq!(inner.producers().time_elapsed(miner), 2 * TICK_MS);
+
+        // with none left there is nothing for the progress to belong to
+        inner.sell(miner, 2).unwrap();
+        assert_eq!(inner.producers().time_elapsed(miner), 0);
     }
 
-    pub fn get_producer(&self, id: &str) -> Option<Arc<Mutex<Box<dyn Producer>>>> {
-        self.inner.lock().unwrap().get_producer(id)
+    #[test]
+    fn removing_a_producer_loses_its_progress() {
+        let mut inner = engine(false);
+        let miner = inner.registry().producer("miner").unwrap();
+
+        inner.process_tick(TICK_MS);
+        inner.remove_producer(miner).unwrap();
+
+        // adding it back starts it from scratch, under the same ID
+        assert_eq!(inner.add_producer(producer("miner", "gold", 300, 3)), Ok(miner));
+        assert_eq!(inner.producers().time_elapsed(miner), 0);
+        inner.process_tick(TICK_MS);
+        assert_eq!(inner.producers().time_elapsed(miner), TICK_MS);
+    }
+
+    #[test]
+    fn removed_producers_have_no_refund() {
+        let mut inner = engine(false);
+        let miner = inner.registry().producer("miner").unwrap();
+
+        inner.remove_producer(miner).unwrap();
+        assert_eq!(inner.refund(miner, 1), Err(Error::UnknownProducer(miner)));
+    }
+
+    /// An engine with 10 coins, which can be exchanged for research at 0.1 research a coin
+    fn exchange() -> (EngineInner, CurrencyId, CurrencyId) {
+        let mut inner = EngineInner::new(EngineConfig { starting_funds: 10.0, ..config() }).unwrap();
+        let coins = inner.add_currency(Currency { id: "coins".to_string(), name: "Coins".to_string() });
+        let research = inner.add_currency(Currency { id: "research".to_string(), name: "Research".to_string() });
+        inner.set_exchange_rate(coins, research, 0.1).unwrap();
+        (inner, coins, research)
     }
 
-    pub fn get_products(&self) -> HashMap<String, f64> {
-        self.inner.lock().unwrap().get_products()
+    #[test]
+    fn exchanging_converts_at_the_rate() {
+        let (mut inner, coins, research) = exchange();
+
+        assert_close(inner.exchange(coins, research, 4.0).unwrap(), 0.4);
+        assert_close(inner.wallet().balance(coins), 6.0);
+        assert_close(inner.wallet().balance(research), 0.4);
+
+        assert!(matches!(inner.exchange(coins, research, 7.0), Err(Error::InsufficientFunds { .. })));
+        assert!(matches!(inner.exchange(research, coins, 0.1), Err(Error::NoExchangeRate { .. })));
+        assert_close(inner.wallet().balance(coins), 6.0);
+    }
+
+    #[test]
+    fn invalid_amounts_and_rates_are_rejected() {
+        let (mut inner, coins, research) = exchange();
+
+        for amount in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(inner.exchange(coins, research, amount), Err(Error::InvalidAmount(_))));
+        }
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(inner.set_exchange_rate(research, coins, rate), Err(Error::InvalidExchangeRate(_))));
+        }
+        assert_close(inner.wallet().balance(coins), 10.0);
+        assert_close(inner.wallet().balance(research), 0.0);
+        assert!(matches!(inner.exchange(research, coins, 1.0), Err(Error::NoExchangeRate { .. })));
+    }
+
+    #[test]
+    fn speed_buffs_keep_the_time_lost_to_rounding() {
+        let mut inner = EngineInner::new(config()).unwrap();
+        inner.add_currency(Currency { id: "coins".to_string(), name: "Coins".to_string() });
+        let miner = inner.add_producer(producer("miner", "gold", 1_000_000, 1)).unwrap();
+        inner.activate_buff(Buff {
+            id: "haste".to_string(),
+            name: "Haste".to_string(),
+            effect: BuffEffect::Speed { producer: miner, multiplier: 1.5 },
+            duration_ms: 1_000_000,
+            cost: Vec::new(),
+        });
+
+        // each 25ms tick is 37.5ms of progress, which would drift to 38ms if rounded every tick
+        for _ in 0..100 {
+            inner.process_tick(25);
+        }
+        assert_eq!(inner.producers().time_elapsed(miner), 3_750);
+    }
+
+    /// The game's content, with world events drawn every `interval_ms` on average
+    fn world(interval_ms: u64) -> EngineInner {
+        let mut inner = EngineInner::new(EngineConfig { world_event_interval_ms: interval_ms, ..config() }).unwrap();
+        for currency in content::currencies() {
+            inner.add_currency(currency);
+        }
+        for producer in content::producers() {
+            inner.add_producer(producer).unwrap();
+        }
+        for event in content::world_events() {
+            inner.add_world_event(event).unwrap();
+        }
+        inner
+    }
+
+    #[test]
+    fn world_events_are_scheduled_from_the_seed() {
+        let mut inner = world(1_000);
+        let delays = (0..8).map(|_| inner.schedule_world_event().unwrap()).collect::<Vec<_>>();
+        assert_eq!(delays, vec![740, 948, 1138, 815, 1233, 1139, 1193, 888]);
+
+        // the same seed gives the same schedule
+        let mut inner = world(1_000);
+        assert_eq!((0..8).map(|_| inner.schedule_world_event().unwrap()).collect::<Vec<_>>(), delays);
+
+        // the delays average out around the interval, never less than half or more than one and a half of it
+        assert!(delays.iter().all(|&d| (500..=1_500).contains(&d)));
+        assert_eq!(world(0).schedule_world_event(), None);
+    }
+
+    #[test]
+    fn world_events_are_drawn_from_the_seed() {
+        let mut inner = world(1_000);
+        let events = inner.subscribe();
+
+        // ten seconds of play at 50ms a tick, noting the tick each event happened in
+        let mut drawn = Vec::new();
+        for _ in 0..200 {
+            inner.process_tick(TICK_MS);
+            for event in events.try_iter() {
+                if let EngineEvent::WorldEvent { name, .. } = event {
+                    drawn.push((inner.tick, name));
+                }
+            }
+        }
+        let drawn = drawn.iter().map(|(tick, name)| (*tick, name.as_str())).collect::<Vec<_>>();
+        assert_eq!(drawn, vec![
+            (15, "Gold Rush"),
+            (38, "Travelling Merchant"),
+            (63, "Gold Rush"),
+            (87, "Forest Fire"),
+            (114, "Forest Fire"),
+            (138, "Forest Fire"),
+            (159, "Forest Fire"),
+            (172, "Travelling Merchant"),
+            (190, "Travelling Merchant"),
+        ]);
+    }
+
+    #[test]
+    fn speed_buffs_drop_their_carried_time_when_they_end() {
+        let mut inner = EngineInner::new(config()).unwrap();
+        inner.add_currency(Currency { id: "coins".to_string(), name: "Coins".to_string() });
+        let miner = inner.add_producer(producer("miner", "gold", 1_000_000, 1)).unwrap();
+        let haste = Buff {
+            id: "haste".to_string(),
+            name: "Haste".to_string(),
+            effect: BuffEffect::Speed { producer: miner, multiplier: 1.5 },
+            duration_ms: 25,
+            cost: Vec::new(),
+        };
+
+        // 25ms at 1.5x is 37.5ms, leaving half a millisecond carried
+        inner.activate_buff(haste.clone());
+        inner.process_tick(25);
+        assert_eq!(inner.producers().time_elapsed(miner), 37);
+        assert!(!inner.speed_carry.contains_key(&miner));
+
+        // without the old half millisecond, the next buff rounds down again rather than making up a whole one
+        inner.activate_buff(haste);
+        inner.process_tick(25);
+        assert_eq!(inner.producers().time_elapsed(miner), 74);
+    }
+
+    fn threaded() -> Engine {
+        Engine::new(EngineConfig { mode: EngineMode::Threaded, tick_timeout_ms: 1, ..config() }).unwrap()
+    }
+
+    /// Waits for the engine to process another tick, failing the test if it doesn't within a second
+    fn wait_for_tick(engine: &Engine) {
+        let start = engine.snapshot().unwrap().tick;
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        while engine.snapshot().unwrap().tick == start {
+            assert!(Instant::now() < deadline, "the engine stopped ticking");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn threaded_engine_restarts_after_stopping() {
+        let mut engine = threaded();
+
+        for _ in 0..3 {
+            engine.start().unwrap();
+            wait_for_tick(&engine);
+            engine.stop().unwrap();
+
+            let stopped_at = engine.snapshot().unwrap().tick;
+            thread::sleep(Duration::from_millis(10));
+            assert_eq!(engine.snapshot().unwrap().tick, stopped_at);
+        }
+    }
+
+    #[test]
+    fn start_and_stop_are_idempotent() {
+        let mut engine = threaded();
+
+        engine.start().unwrap();
+        engine.start().unwrap();
+        wait_for_tick(&engine);
+        engine.stop().unwrap();
+        engine.stop().unwrap();
+
+        let mut engine = Engine::new(config()).unwrap();
+        engine.start().unwrap();
+        engine.start().unwrap();
+        engine.stop().unwrap();
+        engine.stop().unwrap();
+    }
+
+    #[test]
+    fn updates_less_than_a_millisecond_apart_add_up() {
+        let mut engine = Engine::new(config()).unwrap();
+        let start = Instant::now();
+
+        engine.start().unwrap();
+        engine.update(start).unwrap();
+        // 1000 updates 300µs apart make 300ms, which is six whole ticks
+        for i in 1..=1_000 {
+            engine.update(start + Duration::from_micros(300 * i)).unwrap();
+        }
+        assert_eq!(engine.snapshot().unwrap().tick, 6);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn run_processes_a_tick_for_every_item_of_the_stream() {
+        let mut engine = Engine::new(EngineConfig { mode: EngineMode::Async, ..config() }).unwrap();
+        let mut snapshots = engine.snapshots().unwrap();
+
+        futures::executor::block_on(engine.run(futures::stream::iter(0..5)).unwrap()).unwrap();
+
+        assert_eq!(engine.snapshot().unwrap().tick, 5);
+        let ticks = std::iter::from_fn(|| snapshots.try_recv().ok()).map(|s| s.tick).collect::<Vec<_>>();
+        assert_eq!(ticks, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn run_leaves_engines_in_other_modes_stopped() {
+        let mut engine = threaded();
+
+        assert!(matches!(engine.run(futures::stream::iter(0..5)), Err(Error::WrongMode)));
+        match &engine.driver {
+            Driver::Threaded { tick_thread, .. } => assert!(tick_thread.is_none()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dropping_a_running_engine_joins_its_threads() {
+        let mut engine = threaded();
+        engine.start().unwrap();
+        wait_for_tick(&engine);
+
+        // the tick thread holds the only other reference to the engine's state, until it has finished
+        let inner = match &engine.driver {
+            Driver::Threaded { inner, .. } => Arc::downgrade(inner),
+            _ => unreachable!(),
+        };
+        drop(engine);
+        assert!(inner.upgrade().is_none());
     }
 }
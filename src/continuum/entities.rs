@@ -1,31 +1,75 @@
 use std::fmt::Debug;
 
-#[derive(Debug, Clone)]
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::continuum::currency::Cost;
+use crate::continuum::registry::{ActionId, ProducerId, ProductId};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ProductType {
+    /// The stable key the product is registered under
+    pub id: String,
+    /// The name shown to the player
     pub name: String,
     pub production_quantity: f64,
     pub value_per_unit: f64,
+    /// The key of the currency that `value_per_unit` is paid in
+    pub currency: String,
 }
 
 /// Any producer in the system must implement the `Producer` trait
 pub trait Producer: Debug + Send + Sync {
+    /// The stable key the producer is registered under
     fn id(&self) -> &str;
+    /// The name shown to the player
+    fn name(&self) -> &str;
     fn production_time(&self) -> u64;
     fn set_production_time(&mut self, t: u64);
     fn production_quantity(&self) -> f64;
     fn set_production_quantity(&mut self, quantity: f64);
     fn product_type(&self) -> &ProductType;
+    /// The number of units of this producer that are owned
+    fn count(&self) -> u64;
+    fn set_count(&mut self, count: u64);
+    /// Progress made towards the current production cycle, in ms
+    fn time_elapsed(&self) -> u64;
+    fn set_time_elapsed(&mut self, t: u64);
+    /// The price of the unit numbered `index` (the first unit bought is 0)
+    fn unit_cost(&self, index: u64) -> Vec<Cost>;
+    /// The cost of purchasing the next unit of this producer
+    fn cost(&self) -> Vec<Cost> {
+        self.unit_cost(self.count())
+    }
+    /// Whether this producer consumes other products as it produces.
+    /// Producers without inputs can be fast-forwarded by any amount of time in a single `on_tick()` call
+    fn has_inputs(&self) -> bool {
+        false
+    }
     fn on_tick(&mut self, delta: u64) -> f64;
+    /// A copy of this producer, so that an engine holding it can be copied, e.g. to try out different purchases
+    /// on a `Simulation` from the same point
+    fn clone_box(&self) -> Box<dyn Producer>;
+}
+
+impl Clone for Box<dyn Producer> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ProducerEntity {
     pub id: String,
-    pub base_cost: f64,
+    pub name: String,
+    /// The price of the first unit, in any number of currencies
+    pub base_cost: Vec<Cost>,
+    /// How much the price of every currency grows by with each unit bought
     pub cost_coefficient: f64,
     pub product_type: ProductType,
     pub production_time_ms: u64,
     pub time_elapsed: u64,
+    pub count: u64,
 }
 
 impl Producer for ProducerEntity {
@@ -33,6 +77,10 @@ impl Producer for ProducerEntity {
         &self.id
     }
 
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn production_time(&self) -> u64 {
         self.production_time_ms
     }
@@ -53,20 +101,127 @@ impl Producer for ProducerEntity {
         &self.product_type
     }
 
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn set_count(&mut self, count: u64) {
+        self.count = count
+    }
+
+    fn time_elapsed(&self) -> u64 {
+        self.time_elapsed
+    }
+
+    fn set_time_elapsed(&mut self, t: u64) {
+        self.time_elapsed = t
+    }
+
+    fn unit_cost(&self, index: u64) -> Vec<Cost> {
+        scale_costs(&self.base_cost, self.cost_coefficient.powi(index as i32))
+    }
+
     fn on_tick(&mut self, delta: u64) -> f64 {
-        self.time_elapsed += delta;
-
-        let quantity_produced = {
-            if self.time_elapsed > self.production_time_ms {
-                let quantity_produced = (self.time_elapsed as f64 / self.production_time_ms as f64).floor() * self.product_type.production_quantity;
-    
-                self.time_elapsed -= self.production_time_ms * (quantity_produced / self.product_type.production_quantity) as u64;
-                quantity_produced
-            } else {
-                0.0
-            }
-        };
-
-        quantity_produced
-    }
-}
\ No newline at end of file
+        // a producer that hasn't been purchased yet makes no progress
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let cycles = run_cycles(&mut self.time_elapsed, delta, self.production_time_ms);
+
+        cycles as f64 * self.product_type.production_quantity * self.count as f64
+    }
+
+    fn clone_box(&self) -> Box<dyn Producer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Moves a producer's progress on by `delta` ms, leaving `time_elapsed` with the progress made on the current cycle.
+/// Returns the number of cycles completed.
+/// Completed cycles and the leftover progress only depend on the total time elapsed,
+/// so one call with a long delta gives the same result as many calls with short ones.
+/// A producer whose production time has been set to 0 never completes a cycle
+pub fn run_cycles(time_elapsed: &mut u64, delta: u64, production_time_ms: u64) -> u64 {
+    if production_time_ms == 0 {
+        return 0;
+    }
+    let total = *time_elapsed + delta;
+
+    *time_elapsed = total % production_time_ms;
+    total / production_time_ms
+}
+
+/// Something the player does by hand, such as "Mine Gold", which makes its product straight away rather than over time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManualAction {
+    /// The stable key the action is registered under
+    pub id: String,
+    /// The name shown to the player
+    pub name: String,
+    /// What the action makes. `production_quantity` is how much each use yields
+    pub product_type: ProductType,
+    /// Game time that has to pass before the action can be used again, in ms. 0 for none
+    pub cooldown_ms: u64,
+}
+
+/// What an `Upgrade` multiplies the output of
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UpgradeTarget {
+    Producer(ProducerId),
+    /// The yield of each use of a manual action
+    Action(ActionId),
+}
+
+/// A one-off purchase that multiplies what a producer or manual action makes, until the next prestige
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Upgrade {
+    /// The stable key the upgrade is known by; each upgrade can only be applied once
+    pub id: String,
+    /// The name shown to the player
+    pub name: String,
+    pub target: UpgradeTarget,
+    pub cost: Vec<Cost>,
+    /// How much the target's output is multiplied by
+    pub multiplier: f64,
+}
+
+/// What a `Buff` does while it is active
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BuffEffect {
+    /// Multiplies how much of `product` is made
+    Output { product: ProductId, multiplier: f64 },
+    /// Multiplies how quickly `producer` works through its production cycles, e.g. 1.5 for 50% faster
+    Speed { producer: ProducerId, multiplier: f64 },
+}
+
+/// An effect that lasts for a limited amount of game time, such as "2x Gold for 60 seconds"
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Buff {
+    /// The stable key the buff is known by. Activating a buff that is already active restarts its duration
+    pub id: String,
+    /// The name shown to the player
+    pub name: String,
+    pub effect: BuffEffect,
+    pub duration_ms: u64,
+    /// What the buff costs when bought. Buffs granted by events cost nothing
+    pub cost: Vec<Cost>,
+}
+
+/// A buff that is currently in effect
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ActiveBuff {
+    pub buff: Buff,
+    /// Game time left before the buff runs out, in ms
+    pub remaining_ms: u64,
+}
+
+/// Multiplies every amount in `costs` by `factor`
+pub fn scale_costs(costs: &[Cost], factor: f64) -> Vec<Cost> {
+    costs.iter().map(|c| Cost { currency: c.currency.clone(), amount: c.amount * factor }).collect()
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::continuum::registry::{ActionId, ProducerId};
+
+/// Everything that can go wrong in `continuum`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A lock was poisoned by a thread that panicked while holding it
+    LockPoisoned(&'static str),
+    /// A background thread panicked
+    ThreadPanicked(&'static str),
+    /// An `EngineConfig` setting is out of range, e.g. a tick of 0ms
+    InvalidConfig(&'static str),
+    /// `start()` was called while already running
+    AlreadyRunning,
+    /// The operation isn't available in the engine's `EngineMode`
+    WrongMode,
+    UnknownProducer(ProducerId),
+    /// The producer with this key takes 0ms to make its product, so it would complete endless cycles
+    ZeroProductionTime(String),
+    UnknownAction(ActionId),
+    /// The manual action can't be used again for another `remaining_ms`
+    ActionCoolingDown { remaining_ms: u64 },
+    /// No product has been registered with this key
+    UnknownProduct(String),
+    /// There isn't enough of the currency with the key `currency`
+    InsufficientFunds { currency: String, cost: f64, available: f64 },
+    /// No currency has been added with this key
+    UnknownCurrency(String),
+    /// No exchange rate has been set between the currencies with these keys
+    NoExchangeRate { from: String, to: String },
+    /// Amounts to exchange must be finite and more than 0
+    InvalidAmount(f64),
+    /// Exchange rates must be finite and more than 0
+    InvalidExchangeRate(f64),
+    NotEnoughOwned { owned: u64, requested: u64 },
+    /// More units were asked for at once than `MAX_BUY_COUNT`, or than can be owned
+    TooManyUnits { owned: u64, requested: u64 },
+    /// The upgrade with this key has already been applied
+    AlreadyUpgraded(String),
+    /// There is no offer on the table to accept
+    NoOffer,
+    /// Time scales must be finite and not negative
+    InvalidTimeScale(f64),
+    /// The engine was dropped before a queued command could be applied
+    CommandDropped,
+    /// Reading or writing a file failed
+    Io(String),
+    /// A replay file couldn't be read, because of a problem on `line`
+    InvalidReplay { line: usize, reason: String },
+    /// A save file couldn't be read, because of a problem on `line`
+    InvalidSave { line: usize, reason: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::LockPoisoned(what) => write!(f, "the {} lock was poisoned by a panicked thread", what),
+            Error::ThreadPanicked(what) => write!(f, "the {} thread panicked", what),
+            Error::InvalidConfig(reason) => write!(f, "invalid engine config: {}", reason),
+            Error::AlreadyRunning => write!(f, "already running"),
+            Error::WrongMode => write!(f, "not available in this engine mode"),
+            Error::UnknownProducer(id) => write!(f, "no producer with id {:?}", id),
+            Error::ZeroProductionTime(key) => write!(f, "the producer {} has a production time of 0ms", key),
+            Error::UnknownAction(id) => write!(f, "no manual action with id {:?}", id),
+            Error::ActionCoolingDown { remaining_ms } => write!(f, "can't be used again for another {:.1}s", *remaining_ms as f64 / 1000.0),
+            Error::UnknownProduct(key) => write!(f, "no product called {}", key),
+            Error::InsufficientFunds { currency, cost, available } => write!(f, "costs {:.2} {} but only {:.2} is available", cost, currency, available),
+            Error::UnknownCurrency(key) => write!(f, "no currency called {}", key),
+            Error::NoExchangeRate { from, to } => write!(f, "{} can't be exchanged for {}", from, to),
+            Error::InvalidAmount(amount) => write!(f, "{} is not an amount that can be exchanged", amount),
+            Error::InvalidExchangeRate(rate) => write!(f, "{} is not a valid exchange rate", rate),
+            Error::NotEnoughOwned { owned, requested } => write!(f, "only {} owned, can't sell {}", owned, requested),
+            Error::TooManyUnits { owned, requested } => write!(f, "can't buy {} more with {} owned", requested, owned),
+            Error::AlreadyUpgraded(id) => write!(f, "the upgrade {} has already been applied", id),
+            Error::NoOffer => write!(f, "there is no offer to accept"),
+            Error::InvalidTimeScale(scale) => write!(f, "{} is not a valid time scale", scale),
+            Error::CommandDropped => write!(f, "the engine was dropped before the command was applied"),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::InvalidReplay { line, reason } => write!(f, "invalid replay on line {}: {}", line, reason),
+            Error::InvalidSave { line, reason } => write!(f, "invalid save on line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Locks `mutex`, turning a poisoned lock into `Error::LockPoisoned(what)`
+pub fn lock<'a, T>(mutex: &'a Mutex<T>, what: &'static str) -> Result<MutexGuard<'a, T>> {
+    mutex.lock().map_err(|_| Error::LockPoisoned(what))
+}
@@ -0,0 +1,89 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+#[cfg(feature = "async")]
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+
+use crate::continuum::currency::Price;
+use crate::continuum::entities::UpgradeTarget;
+use crate::continuum::registry::{ActionId, CurrencyId, ProducerId, ProductId};
+#[cfg(feature = "async")]
+use crate::continuum::snapshot::Snapshot;
+
+/// Changes to the engine's state that other parts of the game need to hear about
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineEvent {
+    Purchased { id: ProducerId, count: u64, cost: Price },
+    Sold { id: ProducerId, count: u64, refund: Price },
+    Removed { id: ProducerId, refund: Price },
+    Upgraded { target: UpgradeTarget, name: String, multiplier: f64 },
+    ActionPerformed { id: ActionId, product: ProductId, quantity: f64 },
+    Prestiged { level: u32 },
+    BuffStarted { name: String, duration_ms: u64 },
+    BuffEnded { name: String },
+    Exchanged { from: CurrencyId, to: CurrencyId, amount: f64, received: f64 },
+    /// A `WorldEvent` happened
+    WorldEvent { name: String, description: String },
+    ProductDestroyed { product: ProductId, amount: f64 },
+    OfferAccepted { name: String, paid: Price, received: Price },
+    OfferExpired { name: String },
+}
+
+/// Fans `EngineEvent`s out to every subscriber, forgetting subscribers whose receiver has been dropped
+#[derive(Debug, Default)]
+pub struct EventBus {
+    subscribers: Vec<Sender<EngineEvent>>,
+    #[cfg(feature = "async")]
+    stream_subscribers: Vec<UnboundedSender<EngineEvent>>,
+    #[cfg(feature = "async")]
+    snapshot_subscribers: Vec<UnboundedSender<Snapshot>>,
+}
+
+/// A copy of the bus has no subscribers, so that a copy of an engine doesn't send its events to the subscribers of the original
+impl Clone for EventBus {
+    fn clone(&self) -> Self {
+        EventBus::default()
+    }
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self) -> Receiver<EngineEvent> {
+        let (sender, receiver) = channel();
+
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Like `subscribe()`, but events arrive on a `Stream`
+    #[cfg(feature = "async")]
+    pub fn subscribe_stream(&mut self) -> UnboundedReceiver<EngineEvent> {
+        let (sender, receiver) = unbounded();
+
+        self.stream_subscribers.push(sender);
+        receiver
+    }
+
+    #[cfg(feature = "async")]
+    pub fn subscribe_snapshots(&mut self) -> UnboundedReceiver<Snapshot> {
+        let (sender, receiver) = unbounded();
+
+        self.snapshot_subscribers.push(sender);
+        receiver
+    }
+
+    pub fn emit(&mut self, event: EngineEvent) {
+        #[cfg(feature = "async")]
+        self.stream_subscribers.retain(|s| s.unbounded_send(event.clone()).is_ok());
+
+        self.subscribers.retain(|s| s.send(event.clone()).is_ok());
+    }
+
+    #[cfg(feature = "async")]
+    pub fn publish_snapshot(&mut self, snapshot: Snapshot) {
+        self.snapshot_subscribers.retain(|s| s.unbounded_send(snapshot.clone()).is_ok());
+    }
+
+    #[cfg(feature = "async")]
+    pub fn has_snapshot_subscribers(&self) -> bool {
+        !self.snapshot_subscribers.is_empty()
+    }
+}
@@ -0,0 +1,334 @@
+use crate::continuum::currency::Currency;
+use crate::continuum::engine::EngineConfig;
+use crate::continuum::entities::ProducerEntity;
+use crate::continuum::error::Result;
+use crate::continuum::registry::{ProducerId, Registry};
+use crate::continuum::simulation::Simulation;
+
+/// The purchase strategies the `Optimizer` knows how to evaluate
+#[derive(Debug, Copy, Clone)]
+pub enum Strategy {
+    /// Always buy the producer with the lowest cost per unit of output
+    Greedy,
+    /// Buy the producer that pays for itself soonest, including the time spent saving up for it
+    Payback,
+    /// Explore purchase orders one purchase at a time, keeping the `width` most promising orders at each step
+    Beam { width: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct Purchase {
+    pub at_ms: u64,
+    pub producer_id: ProducerId,
+}
+
+/// The outcome of running a single strategy
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub strategy: Strategy,
+    /// `None` if the target wasn't reached within the optimizer's time limit
+    pub time_to_target_ms: Option<u64>,
+    pub purchases: Vec<Purchase>,
+}
+
+/// Compares purchase strategies by how quickly they grow the wallet to a target amount of the primary currency.
+///
+/// Every strategy is played out on a `Simulation`, so the results reflect exactly what the engine would do.
+/// Only the primary currency is planned for, so producers priced in other currencies are never bought.
+#[derive(Debug)]
+pub struct Optimizer {
+    config: EngineConfig,
+    currencies: Vec<Currency>,
+    producers: Vec<ProducerEntity>,
+    registry: Registry,
+    target: f64,
+    max_time_ms: u64,
+}
+
+impl Optimizer {
+    pub fn new(config: EngineConfig, currencies: Vec<Currency>, producers: Vec<ProducerEntity>, target: f64, max_time_ms: u64) -> Result<Self> {
+        let registry = Simulation::new(config, &currencies, &producers)?.registry().clone();
+
+        Ok(Optimizer {
+            config,
+            currencies,
+            producers,
+            registry,
+            target,
+            max_time_ms,
+        })
+    }
+
+    fn simulation(&self) -> Simulation {
+        Simulation::new(self.config, &self.currencies, &self.producers)
+            .expect("the optimizer's producers were checked in Optimizer::new()")
+    }
+
+    /// The registry that the `ProducerId`s in reports refer to
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Runs each strategy and returns the reports ordered from fastest to slowest
+    pub fn compare(&self, strategies: &[Strategy]) -> Vec<Report> {
+        let mut reports = strategies.iter().map(|s| self.run(*s)).collect::<Vec<_>>();
+
+        reports.sort_by_key(|r| r.time_to_target_ms.unwrap_or(u64::MAX));
+        reports
+    }
+
+    pub fn run(&self, strategy: Strategy) -> Report {
+        let (time_to_target_ms, purchases) = match strategy {
+            Strategy::Greedy => self.run_heuristic(|sim, id| sim.cost(id) / sim.unit_rate(id)),
+            Strategy::Payback => self.run_heuristic(|sim, id| Self::wait_for(sim, sim.cost(id)) + sim.cost(id) / sim.unit_rate(id)),
+            Strategy::Beam { width } => self.run_beam(width.max(1)),
+        };
+
+        Report {
+            strategy,
+            time_to_target_ms,
+            purchases,
+        }
+    }
+
+    /// Repeatedly buys whichever producer scores lowest, for as long as buying it brings the target closer
+    fn run_heuristic<F>(&self, score: F) -> (Option<u64>, Vec<Purchase>)
+    where
+        F: Fn(&Simulation, ProducerId) -> f64,
+    {
+        let mut sim = self.simulation();
+        let mut purchases = Vec::new();
+
+        while sim.wallet() < self.target {
+            let candidate = sim.producer_ids().iter()
+                .filter(|id| sim.unit_rate(**id) > 0.0)
+                .map(|id| (score(&sim, *id), *id))
+                .filter(|(s, _)| s.is_finite())
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            match candidate {
+                Some((_, id)) if self.finish_after(&sim, id) < self.finish(&sim) => {
+                    let cost = sim.cost(id);
+                    if !self.wait_until(&mut sim, cost) {
+                        break;
+                    }
+                    if sim.purchase(id).is_err() {
+                        break;
+                    }
+                    purchases.push(Purchase { at_ms: sim.elapsed_ms(), producer_id: id });
+                }
+                _ => {
+                    self.wait_until(&mut sim, self.target);
+                    break;
+                }
+            }
+        }
+
+        (self.reached(&sim), purchases)
+    }
+
+    /// Searches over purchase orders. Each order is scored by when it would reach the target if
+    /// nothing more were bought, and the search ends once a step no longer improves on the best score.
+    /// Each order is played out by carrying on from a copy of the simulation of the order it extends
+    fn run_beam(&self, width: usize) -> (Option<u64>, Vec<Purchase>) {
+        let mut beam = vec![(self.simulation(), Vec::new())];
+        let mut best: Option<(f64, Simulation, Vec<Purchase>)> = None;
+
+        loop {
+            let mut candidates = Vec::new();
+
+            for (sim, purchases) in beam.iter() {
+                for id in self.registry.producer_ids() {
+                    if let Some((next, purchases)) = self.extend(sim, purchases, id) {
+                        candidates.push((self.finish(&next), next, purchases));
+                    }
+                }
+            }
+
+            candidates.retain(|(score, _, _)| score.is_finite());
+            candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            candidates.truncate(width);
+
+            match (candidates.first(), &best) {
+                (Some((score, _, _)), Some((best_score, _, _))) if score >= best_score => break,
+                (None, _) => break,
+                (Some((score, sim, purchases)), _) => best = Some((*score, sim.clone(), purchases.clone())),
+            }
+
+            beam = candidates.into_iter().map(|(_, sim, purchases)| (sim, purchases)).collect();
+        }
+
+        let (mut sim, purchases) = best.map_or_else(|| (self.simulation(), Vec::new()), |(_, sim, purchases)| (sim, purchases));
+
+        self.wait_until(&mut sim, self.target);
+        (self.reached(&sim), purchases)
+    }
+
+    /// Carries on from a copy of `sim`, after `purchases`, buying `id` as soon as it's affordable
+    fn extend(&self, sim: &Simulation, purchases: &[Purchase], id: ProducerId) -> Option<(Simulation, Vec<Purchase>)> {
+        let mut sim = sim.clone();
+        let cost = sim.cost(id);
+
+        if !self.wait_until(&mut sim, cost) {
+            return None;
+        }
+        sim.purchase(id).ok()?;
+
+        let mut purchases = purchases.to_vec();
+        purchases.push(Purchase { at_ms: sim.elapsed_ms(), producer_id: id });
+        Some((sim, purchases))
+    }
+
+    /// Steps the simulation until the wallet holds `amount`. Returns `false` if that can't happen within the time limit
+    fn wait_until(&self, sim: &mut Simulation, amount: f64) -> bool {
+        while sim.wallet() < amount {
+            if sim.income_rate() <= 0.0 || sim.elapsed_ms() >= self.max_time_ms {
+                return false;
+            }
+            sim.step();
+        }
+        true
+    }
+
+    fn reached(&self, sim: &Simulation) -> Option<u64> {
+        if sim.wallet() >= self.target {
+            Some(sim.elapsed_ms())
+        } else {
+            None
+        }
+    }
+
+    /// Estimated time at which the target is reached if nothing else is bought
+    fn finish(&self, sim: &Simulation) -> f64 {
+        sim.elapsed_ms() as f64 + Self::time_to(sim.wallet(), sim.income_rate(), self.target)
+    }
+
+    /// Estimated time at which the target is reached if `id` is bought as soon as possible and nothing after it
+    fn finish_after(&self, sim: &Simulation, id: ProducerId) -> f64 {
+        let cost = sim.cost(id);
+        let income = sim.income_rate();
+        let wait = Self::wait_for(sim, cost);
+        let wallet = sim.wallet() + income * wait - cost;
+
+        sim.elapsed_ms() as f64 + wait + Self::time_to(wallet, income + sim.unit_rate(id), self.target)
+    }
+
+    fn wait_for(sim: &Simulation, amount: f64) -> f64 {
+        Self::time_to(sim.wallet(), sim.income_rate(), amount)
+    }
+
+    fn time_to(wallet: f64, rate: f64, amount: f64) -> f64 {
+        if wallet >= amount {
+            0.0
+        } else if rate > 0.0 {
+            (amount - wallet) / rate
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuum::currency::Cost;
+    use crate::continuum::engine::EngineMode;
+    use crate::continuum::entities::ProductType;
+
+    fn producer(id: &str, cost: f64, value: f64, production_time_ms: u64) -> ProducerEntity {
+        ProducerEntity {
+            id: id.to_string(),
+            name: id.to_string(),
+            base_cost: vec![Cost::new("coins", cost)],
+            cost_coefficient: 1.25,
+            product_type: ProductType {
+                id: id.to_string(),
+                name: id.to_string(),
+                production_quantity: 1.0,
+                value_per_unit: value,
+                currency: "coins".to_string(),
+            },
+            production_time_ms,
+            time_elapsed: 0,
+            count: 0,
+        }
+    }
+
+    fn optimizer() -> Optimizer {
+        let config = EngineConfig {
+            mode: EngineMode::SingleThreaded,
+            tick_timeout_ms: 100,
+            starting_funds: 2.0,
+            sell_refund_ratio: 0.5,
+            prestige_bonus: 0.0,
+            seed: 1,
+            world_event_interval_ms: 0,
+        };
+        let currencies = vec![Currency { id: "coins".to_string(), name: "Coins".to_string() }];
+        let producers = vec![producer("stall", 1.0, 1.0, 1_000), producer("mill", 12.0, 15.0, 2_000)];
+
+        Optimizer::new(config, currencies, producers, 60.0, 600_000).unwrap()
+    }
+
+    /// The producers bought by `report`, by key, and when
+    fn purchases(optimizer: &Optimizer, report: &Report) -> Vec<(String, u64)> {
+        report.purchases.iter()
+            .map(|p| (optimizer.registry().producer_key(p.producer_id).to_string(), p.at_ms))
+            .collect()
+    }
+
+    fn order(purchases: &[(&str, u64)]) -> Vec<(String, u64)> {
+        purchases.iter().map(|(key, at_ms)| (key.to_string(), *at_ms)).collect()
+    }
+
+    #[test]
+    fn greedy_buys_the_cheapest_output_first() {
+        let optimizer = optimizer();
+        let report = optimizer.run(Strategy::Greedy);
+
+        assert_eq!(report.time_to_target_ms, Some(12_000));
+        assert_eq!(purchases(&optimizer, &report), order(&[
+            ("stall", 0), ("stall", 1_000), ("stall", 2_000), ("mill", 6_000), ("stall", 7_000), ("mill", 8_000), ("stall", 8_000),
+        ]));
+    }
+
+    #[test]
+    fn payback_buys_what_pays_for_itself_soonest() {
+        let optimizer = optimizer();
+        let report = optimizer.run(Strategy::Payback);
+
+        assert_eq!(report.time_to_target_ms, Some(11_000));
+        assert_eq!(purchases(&optimizer, &report), order(&[
+            ("stall", 0), ("stall", 1_000), ("stall", 2_000), ("stall", 3_000), ("stall", 4_000), ("stall", 4_000),
+            ("mill", 6_000), ("mill", 8_000),
+        ]));
+    }
+
+    #[test]
+    fn beam_keeps_the_most_promising_orders() {
+        let optimizer = optimizer();
+        let report = optimizer.run(Strategy::Beam { width: 3 });
+
+        assert_eq!(report.time_to_target_ms, Some(13_000));
+        assert_eq!(purchases(&optimizer, &report), order(&[
+            ("stall", 0), ("stall", 1_000), ("mill", 7_000), ("stall", 8_000), ("mill", 9_000), ("stall", 9_000),
+        ]));
+    }
+
+    #[test]
+    fn reports_are_compared_fastest_first() {
+        let reports = optimizer().compare(&[Strategy::Beam { width: 3 }, Strategy::Greedy, Strategy::Payback]);
+        let times = reports.iter().map(|r| r.time_to_target_ms).collect::<Vec<_>>();
+
+        assert_eq!(times, vec![Some(11_000), Some(12_000), Some(13_000)]);
+        assert!(matches!(reports[0].strategy, Strategy::Payback));
+    }
+
+    #[test]
+    fn unreachable_targets_have_no_time() {
+        let optimizer = Optimizer { max_time_ms: 5_000, ..optimizer() };
+
+        assert_eq!(optimizer.run(Strategy::Greedy).time_to_target_ms, None);
+        assert_eq!(optimizer.run(Strategy::Beam { width: 2 }).time_to_target_ms, None);
+    }
+}
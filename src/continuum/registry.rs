@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A stable handle to a producer, interned from the producer's key
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProducerId(u32);
+
+/// A stable handle to a product, interned from the product's key
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProductId(u32);
+
+/// A stable handle to a currency, interned from the currency's key
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CurrencyId(u32);
+
+/// A stable handle to a manual action, interned from the action's key
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ActionId(u32);
+
+impl ProducerId {
+    /// The ID of the producer registered `index`th, counting from 0, e.g. as numbered in a key bindings file.
+    /// IDs are handed out in order and never reused, so this is the same producer for as long as the game runs
+    pub fn from_index(index: usize) -> Self {
+        ProducerId(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl ProductId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl CurrencyId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl ActionId {
+    /// The ID of the manual action registered `index`th, counting from 0, as for `ProducerId::from_index()`
+    pub fn from_index(index: usize) -> Self {
+        ActionId(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The key and display name an ID was registered with
+#[derive(Debug, Clone)]
+struct Names {
+    key: String,
+    display_name: String,
+}
+
+/// Interns the keys of producers, products, currencies and manual actions (e.g. `"gold"`) into IDs, and maps those IDs to
+/// the names shown to the player (e.g. `"Gold Miner"`).
+///
+/// Keys never change once registered, so display names can be changed freely without
+/// disturbing anything that refers to a producer or product by ID.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    producer_ids: HashMap<String, ProducerId>,
+    producers: Vec<Names>,
+    product_ids: HashMap<String, ProductId>,
+    products: Vec<Names>,
+    currency_ids: HashMap<String, CurrencyId>,
+    currencies: Vec<Names>,
+    action_ids: HashMap<String, ActionId>,
+    actions: Vec<Names>,
+}
+
+impl Registry {
+    /// Returns the ID for the producer `key`, registering it if it hasn't been seen before
+    pub fn intern_producer(&mut self, key: &str, display_name: &str) -> ProducerId {
+        if let Some(id) = self.producer_ids.get(key) {
+            return *id;
+        }
+
+        let id = ProducerId(self.producers.len() as u32);
+        self.producers.push(Names { key: key.to_string(), display_name: display_name.to_string() });
+        self.producer_ids.insert(key.to_string(), id);
+        id
+    }
+
+    /// Returns the ID for the product `key`, registering it if it hasn't been seen before
+    pub fn intern_product(&mut self, key: &str, display_name: &str) -> ProductId {
+        if let Some(id) = self.product_ids.get(key) {
+            return *id;
+        }
+
+        let id = ProductId(self.products.len() as u32);
+        self.products.push(Names { key: key.to_string(), display_name: display_name.to_string() });
+        self.product_ids.insert(key.to_string(), id);
+        id
+    }
+
+    /// Returns the ID for the currency `key`, registering it if it hasn't been seen before
+    pub fn intern_currency(&mut self, key: &str, display_name: &str) -> CurrencyId {
+        if let Some(id) = self.currency_ids.get(key) {
+            return *id;
+        }
+
+        let id = CurrencyId(self.currencies.len() as u32);
+        self.currencies.push(Names { key: key.to_string(), display_name: display_name.to_string() });
+        self.currency_ids.insert(key.to_string(), id);
+        id
+    }
+
+    /// Returns the ID for the manual action `key`, registering it if it hasn't been seen before
+    pub fn intern_action(&mut self, key: &str, display_name: &str) -> ActionId {
+        if let Some(id) = self.action_ids.get(key) {
+            return *id;
+        }
+
+        let id = ActionId(self.actions.len() as u32);
+        self.actions.push(Names { key: key.to_string(), display_name: display_name.to_string() });
+        self.action_ids.insert(key.to_string(), id);
+        id
+    }
+
+    pub fn producer(&self, key: &str) -> Option<ProducerId> {
+        self.producer_ids.get(key).copied()
+    }
+
+    pub fn product(&self, key: &str) -> Option<ProductId> {
+        self.product_ids.get(key).copied()
+    }
+
+    pub fn currency(&self, key: &str) -> Option<CurrencyId> {
+        self.currency_ids.get(key).copied()
+    }
+
+    pub fn action(&self, key: &str) -> Option<ActionId> {
+        self.action_ids.get(key).copied()
+    }
+
+    pub fn producer_ids(&self) -> impl Iterator<Item = ProducerId> {
+        (0..self.producers.len() as u32).map(ProducerId)
+    }
+
+    pub fn product_ids(&self) -> impl Iterator<Item = ProductId> {
+        (0..self.products.len() as u32).map(ProductId)
+    }
+
+    pub fn currency_ids(&self) -> impl Iterator<Item = CurrencyId> {
+        (0..self.currencies.len() as u32).map(CurrencyId)
+    }
+
+    pub fn action_ids(&self) -> impl Iterator<Item = ActionId> {
+        (0..self.actions.len() as u32).map(ActionId)
+    }
+
+    pub fn producer_key(&self, id: ProducerId) -> &str {
+        &self.producers[id.index()].key
+    }
+
+    pub fn producer_name(&self, id: ProducerId) -> &str {
+        &self.producers[id.index()].display_name
+    }
+
+    pub fn product_key(&self, id: ProductId) -> &str {
+        &self.products[id.index()].key
+    }
+
+    pub fn product_name(&self, id: ProductId) -> &str {
+        &self.products[id.index()].display_name
+    }
+
+    pub fn currency_key(&self, id: CurrencyId) -> &str {
+        &self.currencies[id.index()].key
+    }
+
+    pub fn currency_name(&self, id: CurrencyId) -> &str {
+        &self.currencies[id.index()].display_name
+    }
+
+    pub fn action_key(&self, id: ActionId) -> &str {
+        &self.actions[id.index()].key
+    }
+
+    pub fn action_name(&self, id: ActionId) -> &str {
+        &self.actions[id.index()].display_name
+    }
+
+    pub fn set_producer_name(&mut self, id: ProducerId, display_name: &str) {
+        self.producers[id.index()].display_name = display_name.to_string();
+    }
+
+    pub fn set_product_name(&mut self, id: ProductId, display_name: &str) {
+        self.products[id.index()].display_name = display_name.to_string();
+    }
+}
@@ -0,0 +1,448 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::continuum::commands::EngineCommand;
+use crate::continuum::currency::{Cost, Wallet};
+use crate::continuum::entities::{Buff, BuffEffect, Upgrade, UpgradeTarget};
+use crate::continuum::error::{Error, Result};
+use crate::continuum::registry::{ActionId, CurrencyId, ProducerId, ProductId, Registry};
+use crate::continuum::simulation::Simulation;
+use crate::continuum::snapshot::Snapshot;
+
+const HEADER: &str = "replay 4";
+
+/// A command as it was applied by the engine, at the start of tick number `tick`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedCommand {
+    pub tick: u64,
+    pub command: EngineCommand,
+}
+
+/// Every command applied to an engine since it was created, and the state it ended in.
+///
+/// Playing a replay back with `play()` gives exactly the recorded end state as long as the engine ticked in steps
+/// of `EngineConfig::tick_timeout_ms`, as `EngineMode::SingleThreaded` does. Ticks in `EngineMode::Threaded` last
+/// as long as the timer took to fire, so those replays only come close
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    /// The seed the engine's random number generator started from, so that world events happen at the same times
+    pub seed: u64,
+    pub commands: Vec<RecordedCommand>,
+    /// The number of ticks the engine had processed when the replay was taken
+    pub end_tick: u64,
+    pub end_wallet: Wallet,
+}
+
+impl Replay {
+    /// Runs the recorded commands against `simulation`, which should be freshly created with the same config,
+    /// producers and world events as the recorded engine. The simulation is reseeded with the recorded seed.
+    /// Returns the state at `end_tick`
+    pub fn play(&self, simulation: &mut Simulation) -> Snapshot {
+        simulation.set_seed(self.seed);
+
+        let mut commands = self.commands.iter().peekable();
+
+        while simulation.tick() < self.end_tick {
+            while let Some(recorded) = commands.next_if(|c| c.tick <= simulation.tick()) {
+                simulation.send(recorded.command.clone());
+            }
+            simulation.step();
+        }
+        simulation.snapshot()
+    }
+
+    /// Writes the replay to `path`. Producers are written by key rather than ID, looked up in `registry`
+    pub fn save<P: AsRef<Path>>(&self, path: P, registry: &Registry) -> Result<()> {
+        fs::write(path, self.to_text(registry)).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    /// Reads a replay written by `save()`, looking producer keys up in `registry`
+    pub fn load<P: AsRef<Path>>(path: P, registry: &Registry) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+        Self::parse(&text, registry)
+    }
+
+    /// `seed\t<seed>`, then one line per command, as `<tick>\t<command>\t<arguments...>`, then
+    /// `end\t<tick>\t<currency>=<balance>...`. Costs are written as `<currency>=<amount>,...`.
+    /// Keys and names are escaped with `escape()`, so they can contain the separators
+    pub fn to_text(&self, registry: &Registry) -> String {
+        let mut text = format!("{}\nseed\t{}\n", HEADER, self.seed);
+
+        for RecordedCommand { tick, command } in &self.commands {
+            let _ = match command {
+                EngineCommand::Buy { id, count } => writeln!(text, "{}\tbuy\t{}\t{}", tick, escape(registry.producer_key(*id)), count),
+                EngineCommand::Sell { id, count } => writeln!(text, "{}\tsell\t{}\t{}", tick, escape(registry.producer_key(*id)), count),
+                EngineCommand::Remove(id) => writeln!(text, "{}\tremove\t{}", tick, escape(registry.producer_key(*id))),
+                EngineCommand::ApplyUpgrade(u) => {
+                    let (kind, target) = match u.target {
+                        UpgradeTarget::Producer(id) => ("producer", registry.producer_key(id)),
+                        UpgradeTarget::Action(id) => ("action", registry.action_key(id)),
+                    };
+                    writeln!(text, "{}\tupgrade\t{}\t{}\t{}\t{}\t{}\t{}",
+                        tick, escape(&u.id), escape(&u.name), kind, escape(target), write_costs(&u.cost), u.multiplier)
+                }
+                EngineCommand::PerformAction(id) => writeln!(text, "{}\taction\t{}", tick, escape(registry.action_key(*id))),
+                EngineCommand::BuyBuff(b) => {
+                    let (effect, target, multiplier) = match b.effect {
+                        BuffEffect::Output { product, multiplier } => ("output", registry.product_key(product), multiplier),
+                        BuffEffect::Speed { producer, multiplier } => ("speed", registry.producer_key(producer), multiplier),
+                    };
+                    writeln!(text, "{}\tbuff\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        tick, escape(&b.id), escape(&b.name), write_costs(&b.cost), b.duration_ms, effect, escape(target), multiplier)
+                }
+                EngineCommand::Prestige => writeln!(text, "{}\tprestige", tick),
+                EngineCommand::SetTimeScale(scale) => writeln!(text, "{}\ttimescale\t{}", tick, scale),
+                EngineCommand::Advance { elapsed_ms } => writeln!(text, "{}\tadvance\t{}", tick, elapsed_ms),
+                EngineCommand::Exchange { from, to, amount } => writeln!(text, "{}\texchange\t{}\t{}\t{}",
+                    tick, escape(registry.currency_key(*from)), escape(registry.currency_key(*to)), amount),
+                EngineCommand::AcceptOffer => writeln!(text, "{}\taccept", tick),
+            };
+        }
+
+        let _ = write!(text, "end\t{}", self.end_tick);
+        for id in registry.currency_ids() {
+            let _ = write!(text, "\t{}={}", escape(registry.currency_key(id)), self.end_wallet.balance(id));
+        }
+        text.push('\n');
+        text
+    }
+
+    pub fn parse(text: &str, registry: &Registry) -> Result<Self> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(invalid(1, format!("expected '{}'", HEADER))),
+        }
+
+        let seed = match lines.next() {
+            Some((number, line)) => {
+                let fields = line.split('\t').collect::<Vec<_>>();
+                let parser = LineParser { number, fields: &fields, registry };
+
+                if fields[0] != "seed" {
+                    return Err(invalid(number, "expected the seed".to_string()));
+                }
+                parser.number(1)?
+            }
+            None => return Err(invalid(2, "expected the seed".to_string())),
+        };
+
+        let mut commands = Vec::new();
+        for (number, line) in lines {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let parser = LineParser { number, fields: &fields, registry };
+
+            if fields[0] == "end" {
+                let mut end_wallet = Wallet::default();
+                for index in 2..fields.len() {
+                    let (currency, balance) = parser.amount(index)?;
+                    end_wallet.deposit(currency, balance);
+                }
+
+                return Ok(Replay {
+                    seed,
+                    commands,
+                    end_tick: parser.number(1)?,
+                    end_wallet,
+                });
+            }
+
+            let command = match fields.get(1).copied() {
+                Some("buy") => EngineCommand::Buy { id: parser.producer(2)?, count: parser.number(3)? },
+                Some("sell") => EngineCommand::Sell { id: parser.producer(2)?, count: parser.number(3)? },
+                Some("remove") => EngineCommand::Remove(parser.producer(2)?),
+                Some("upgrade") => EngineCommand::ApplyUpgrade(Upgrade {
+                    id: parser.text(2)?,
+                    name: parser.text(3)?,
+                    target: match parser.field(4)? {
+                        "producer" => UpgradeTarget::Producer(parser.producer(5)?),
+                        "action" => UpgradeTarget::Action(parser.action(5)?),
+                        other => return Err(invalid(number, format!("unknown upgrade target '{}'", other))),
+                    },
+                    cost: parser.costs(6)?,
+                    multiplier: parser.number(7)?,
+                }),
+                Some("action") => EngineCommand::PerformAction(parser.action(2)?),
+                Some("buff") => EngineCommand::BuyBuff(Buff {
+                    id: parser.text(2)?,
+                    name: parser.text(3)?,
+                    cost: parser.costs(4)?,
+                    duration_ms: parser.number(5)?,
+                    effect: match parser.field(6)? {
+                        "output" => BuffEffect::Output { product: parser.product(7)?, multiplier: parser.number(8)? },
+                        "speed" => BuffEffect::Speed { producer: parser.producer(7)?, multiplier: parser.number(8)? },
+                        other => return Err(invalid(number, format!("unknown buff effect '{}'", other))),
+                    },
+                }),
+                Some("prestige") => EngineCommand::Prestige,
+                Some("timescale") => EngineCommand::SetTimeScale(parser.number(2)?),
+                Some("advance") => EngineCommand::Advance { elapsed_ms: parser.number(2)? },
+                Some("exchange") => EngineCommand::Exchange {
+                    from: parser.currency(2)?,
+                    to: parser.currency(3)?,
+                    amount: parser.number(4)?,
+                },
+                Some("accept") => EngineCommand::AcceptOffer,
+                _ => return Err(invalid(number, format!("unknown command in '{}'", line))),
+            };
+            commands.push(RecordedCommand { tick: parser.number(0)?, command });
+        }
+        Err(invalid(text.lines().count(), "missing the 'end' line".to_string()))
+    }
+}
+
+fn write_costs(costs: &[Cost]) -> String {
+    costs.iter().map(|c| format!("{}={}", escape(&c.currency), c.amount)).collect::<Vec<_>>().join(",")
+}
+
+/// Writes the characters that separate fields and lines in a replay or a statistics save as `%` and their hex code,
+/// e.g. a tab as `%09`, so that keys and names can contain them
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '%' | '\t' | '\n' | '\r' | ',' | '=' => {
+                let _ = write!(escaped, "%{:02X}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Undoes `escape()`, or returns `None` if `text` has a `%` that isn't followed by a hex code
+pub(crate) fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(i) = rest.find('%') {
+        unescaped.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 3).filter(|code| code.chars().all(|c| c.is_ascii_hexdigit()))?;
+        unescaped.push(u8::from_str_radix(code, 16).ok()? as char);
+        rest = &rest[i + 3..];
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
+fn invalid(line: usize, reason: String) -> Error {
+    Error::InvalidReplay { line, reason }
+}
+
+/// Reads the tab separated fields of one line of a replay
+struct LineParser<'a> {
+    number: usize,
+    fields: &'a [&'a str],
+    registry: &'a Registry,
+}
+
+impl LineParser<'_> {
+    fn field(&self, index: usize) -> Result<&str> {
+        self.fields.get(index).copied()
+            .ok_or_else(|| invalid(self.number, format!("missing field {}", index)))
+    }
+
+    /// Reads a key or name written with `escape()`
+    fn text(&self, index: usize) -> Result<String> {
+        let field = self.field(index)?;
+        unescape(field).ok_or_else(|| invalid(self.number, format!("'{}' is badly escaped", field)))
+    }
+
+    fn number<T: std::str::FromStr>(&self, index: usize) -> Result<T> {
+        let field = self.field(index)?;
+        field.parse().map_err(|_| invalid(self.number, format!("'{}' is not a number", field)))
+    }
+
+    fn producer(&self, index: usize) -> Result<ProducerId> {
+        let key = self.text(index)?;
+        self.registry.producer(&key).ok_or_else(|| invalid(self.number, format!("no producer called '{}'", key)))
+    }
+
+    fn product(&self, index: usize) -> Result<ProductId> {
+        let key = self.text(index)?;
+        self.registry.product(&key).ok_or_else(|| invalid(self.number, format!("no product called '{}'", key)))
+    }
+
+    fn action(&self, index: usize) -> Result<ActionId> {
+        let key = self.text(index)?;
+        self.registry.action(&key).ok_or_else(|| invalid(self.number, format!("no manual action called '{}'", key)))
+    }
+
+    fn currency(&self, index: usize) -> Result<CurrencyId> {
+        let key = self.text(index)?;
+        self.registry.currency(&key).ok_or_else(|| invalid(self.number, format!("no currency called '{}'", key)))
+    }
+
+    /// Reads a `<currency>=<amount>` field
+    fn amount(&self, index: usize) -> Result<(CurrencyId, f64)> {
+        let field = self.field(index)?;
+        let (key, amount) = field.split_once('=').ok_or_else(|| invalid(self.number, format!("'{}' is not an amount", field)))?;
+        let key = unescape(key).ok_or_else(|| invalid(self.number, format!("'{}' is badly escaped", key)))?;
+        let currency = self.registry.currency(&key).ok_or_else(|| invalid(self.number, format!("no currency called '{}'", key)))?;
+        let amount = amount.parse().map_err(|_| invalid(self.number, format!("'{}' is not a number", amount)))?;
+
+        Ok((currency, amount))
+    }
+
+    /// Reads a field of costs written by `write_costs()`
+    fn costs(&self, index: usize) -> Result<Vec<Cost>> {
+        let field = self.field(index)?;
+
+        field.split(',').filter(|c| !c.is_empty()).map(|cost| {
+            let (currency, amount) = cost.split_once('=').ok_or_else(|| invalid(self.number, format!("'{}' is not a cost", cost)))?;
+            let currency = unescape(currency).ok_or_else(|| invalid(self.number, format!("'{}' is badly escaped", currency)))?;
+            let amount = amount.parse().map_err(|_| invalid(self.number, format!("'{}' is not a number", amount)))?;
+
+            Ok(Cost::new(&currency, amount))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::content;
+    use crate::continuum::engine::{Engine, EngineConfig, EngineMode};
+    use crate::continuum::events::EngineEvent;
+
+    #[test]
+    fn names_with_separators_survive_a_round_trip() {
+        let mut registry = Registry::default();
+        let coins = registry.intern_currency("coins=1,2", "Coins");
+        let miner = registry.intern_producer("gold\tminer", "Gold Miner");
+        let gold = registry.intern_product("gold%", "Gold");
+        let mut end_wallet = Wallet::default();
+        end_wallet.deposit(coins, 12.5);
+
+        let replay = Replay {
+            seed: 7,
+            commands: vec![
+                RecordedCommand { tick: 1, command: EngineCommand::Buy { id: miner, count: 2 } },
+                RecordedCommand { tick: 2, command: EngineCommand::ApplyUpgrade(Upgrade {
+                    id: "picks\n2".to_string(),
+                    name: "Sharp\tPicks, 100% = better".to_string(),
+                    target: UpgradeTarget::Producer(miner),
+                    cost: vec![Cost::new("coins=1,2", 5.0)],
+                    multiplier: 2.0,
+                }) },
+                RecordedCommand { tick: 3, command: EngineCommand::BuyBuff(Buff {
+                    id: "frenzy".to_string(),
+                    name: "Gold\r\nFrenzy".to_string(),
+                    effect: BuffEffect::Output { product: gold, multiplier: 2.0 },
+                    duration_ms: 30_000,
+                    cost: Vec::new(),
+                }) },
+                RecordedCommand { tick: 4, command: EngineCommand::Advance { elapsed_ms: 60_000 } },
+                RecordedCommand { tick: 1204, command: EngineCommand::Remove(miner) },
+            ],
+            end_tick: 1300,
+            end_wallet,
+        };
+
+        let text = replay.to_text(&registry);
+        assert_eq!(text.lines().count(), 8);
+        assert_eq!(Replay::parse(&text, &registry), Ok(replay));
+    }
+
+    #[test]
+    fn badly_escaped_names_are_rejected() {
+        let mut registry = Registry::default();
+        registry.intern_currency("coins", "Coins");
+
+        let text = format!("{}\nseed\t1\n0\tupgrade\tpicks\tSharp %G1\n", HEADER);
+        assert!(matches!(Replay::parse(&text, &registry), Err(Error::InvalidReplay { line: 3, .. })));
+    }
+
+    fn config() -> EngineConfig {
+        EngineConfig {
+            mode: EngineMode::SingleThreaded,
+            tick_timeout_ms: 50,
+            starting_funds: 5.0,
+            sell_refund_ratio: 0.5,
+            prestige_bonus: 0.1,
+            seed: 42,
+            // often enough for several events to happen
+            world_event_interval_ms: 1_000,
+        }
+    }
+
+    /// Updates `engine` every 7ms of `duration` after `*now`, so that updates don't line up with ticks
+    fn play_for(engine: &mut Engine, now: &mut Instant, duration: Duration) {
+        let end = *now + duration;
+
+        while *now < end {
+            *now += Duration::from_millis(7);
+            engine.update(*now).unwrap();
+        }
+    }
+
+    #[test]
+    fn recorded_games_play_back_exactly() {
+        let mut engine = Engine::new(config()).unwrap();
+        for currency in content::currencies() {
+            engine.add_currency(currency).unwrap();
+        }
+        let coins = engine.currency_id("coins").unwrap().unwrap();
+        let research = engine.currency_id("research").unwrap().unwrap();
+        engine.set_exchange_rate(coins, research, 0.1).unwrap();
+        for producer in content::producers() {
+            engine.add_producer(producer).unwrap();
+        }
+        let mine = content::actions().into_iter().map(|a| engine.add_action(a).unwrap()).next().unwrap();
+        for event in content::world_events() {
+            engine.add_world_event(event).unwrap();
+        }
+        let registry = engine.registry().unwrap();
+        let (gold, wood) = (registry.producer("gold").unwrap(), registry.producer("wood").unwrap());
+        let events = engine.subscribe().unwrap();
+
+        engine.record().unwrap();
+        engine.start().unwrap();
+        let mut now = Instant::now();
+        engine.update(now).unwrap();
+
+        engine.send(EngineCommand::Buy { id: gold, count: 2 }).unwrap();
+        engine.send(EngineCommand::Buy { id: wood, count: 1 }).unwrap();
+        play_for(&mut engine, &mut now, Duration::from_secs(2));
+        engine.send(EngineCommand::PerformAction(mine)).unwrap();
+        engine.send(EngineCommand::BuyBuff(Buff {
+            id: "frenzy".to_string(),
+            name: "Wood Frenzy".to_string(),
+            effect: BuffEffect::Output { product: registry.product("wood").unwrap(), multiplier: 2.0 },
+            duration_ms: 3_000,
+            cost: vec![Cost::new("coins", 1.0)],
+        })).unwrap();
+        play_for(&mut engine, &mut now, Duration::from_secs(3));
+        engine.send(EngineCommand::Sell { id: gold, count: 1 }).unwrap();
+        engine.send(EngineCommand::Exchange { from: coins, to: research, amount: 1.0 }).unwrap();
+        engine.send(EngineCommand::Buy { id: wood, count: 1 }).unwrap();
+        play_for(&mut engine, &mut now, Duration::from_secs(5));
+
+        assert!(events.try_iter().any(|e| matches!(e, EngineEvent::WorldEvent { .. })), "no world events happened");
+
+        // played back from its saved form, as the game does
+        let recorded = engine.replay().unwrap().unwrap();
+        let replay = Replay::parse(&recorded.to_text(&registry), &registry).unwrap();
+        assert_eq!(replay, recorded);
+
+        let mut simulation = Simulation::new(config(), &content::currencies(), &content::producers()).unwrap();
+        simulation.set_exchange_rate(coins, research, 0.1).unwrap();
+        for action in content::actions() {
+            simulation.add_action(action).unwrap();
+        }
+        for event in content::world_events() {
+            simulation.add_world_event(event).unwrap();
+        }
+        let played = replay.play(&mut simulation);
+
+        assert_eq!(played, engine.snapshot().unwrap());
+        for balance in &played.wallet {
+            assert_eq!(balance.balance, replay.end_wallet.balance(balance.id));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::continuum::commands::{EngineCommand, PendingCommand};
+use crate::continuum::currency::Currency;
+use crate::continuum::engine::{EngineConfig, EngineInner};
+use crate::continuum::entities::{ManualAction, ProducerEntity};
+use crate::continuum::error::Result;
+use crate::continuum::registry::{ActionId, CurrencyId, ProducerId, Registry};
+use crate::continuum::snapshot::Snapshot;
+use crate::continuum::world::WorldEvent;
+
+/// A headless, deterministic stand-in for `Engine`.
+///
+/// The simulation drives the same `EngineInner` that `Engine` uses, but time only moves forward
+/// when `step()` is called, so no timer thread is involved and a run can be repeated exactly.
+///
+/// `wallet()`, `cost()` and the rates only count the primary currency, which is what the `Optimizer` plans for.
+/// A clone carries on from the same point independently, so different purchases can be tried from there.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    inner: EngineInner,
+    producer_ids: Vec<ProducerId>,
+    elapsed_ms: u64,
+}
+
+impl Simulation {
+    pub fn new(config: EngineConfig, currencies: &[Currency], producers: &[ProducerEntity]) -> Result<Self> {
+        let mut inner = EngineInner::new(config)?;
+        for currency in currencies {
+            inner.add_currency(currency.clone());
+        }
+        let producer_ids = producers.iter().map(|p| inner.add_producer(p.clone())).collect::<Result<_>>()?;
+
+        Ok(Simulation {
+            inner,
+            producer_ids,
+            elapsed_ms: 0,
+        })
+    }
+
+    /// Advance the simulation by a single engine tick
+    pub fn step(&mut self) {
+        let tick = self.inner.config().tick_timeout_ms;
+
+        self.inner.process_tick(tick);
+        self.elapsed_ms += tick;
+    }
+
+    /// The number of ticks stepped so far
+    pub fn tick(&self) -> u64 {
+        self.inner.tick()
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed_ms
+    }
+
+    /// The balance of the primary currency
+    pub fn wallet(&self) -> f64 {
+        self.inner.primary_currency().map_or(0.0, |c| self.balance(c))
+    }
+
+    pub fn balance(&self, currency: CurrencyId) -> f64 {
+        self.inner.wallet().balance(currency)
+    }
+
+    pub fn producer_ids(&self) -> &[ProducerId] {
+        &self.producer_ids
+    }
+
+    pub fn registry(&self) -> &Registry {
+        self.inner.registry()
+    }
+
+    /// Buy one more unit of the producer `id`, paying for it from the wallet
+    pub fn purchase(&mut self, id: ProducerId) -> Result<()> {
+        self.inner.buy(id, 1).map(|_| ())
+    }
+
+    /// Let `from` be exchanged for `to`, as `Engine::set_exchange_rate()` does
+    pub fn set_exchange_rate(&mut self, from: CurrencyId, to: CurrencyId, rate: f64) -> Result<()> {
+        self.inner.set_exchange_rate(from, to, rate)
+    }
+
+    /// Add a manual action, as `Engine::add_action()` does
+    pub fn add_action(&mut self, action: ManualAction) -> Result<ActionId> {
+        self.inner.add_action(action)
+    }
+
+    /// Add an event to the table that world events are drawn from, as `Engine::add_world_event()` does
+    pub fn add_world_event(&mut self, event: WorldEvent) -> Result<()> {
+        self.inner.add_world_event(event)
+    }
+
+    /// Restart the random number generator from `seed`, so that the simulation draws the same world events
+    /// as a game played with that seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.inner.set_seed(seed);
+    }
+
+    /// Queue `command` to be applied at the start of the next step, as `Engine::send()` does
+    pub fn send(&mut self, command: EngineCommand) -> PendingCommand {
+        self.inner.queue(command)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+
+    /// How much of the primary currency the next unit of the producer `id` costs
+    pub fn cost(&self, id: ProducerId) -> f64 {
+        let price = self.inner.price(&self.inner.producers().cost(id));
+
+        match (price, self.inner.primary_currency()) {
+            (Ok(price), Some(primary)) => price.amount(primary),
+            _ => f64::INFINITY,
+        }
+    }
+
+    /// The value a single unit of the producer `id` adds to the primary currency per millisecond
+    pub fn unit_rate(&self, id: ProducerId) -> f64 {
+        let producers = self.inner.producers();
+        let product_type = producers.product_type(id);
+
+        if Some(self.inner.product_currency(producers.product(id))) != self.inner.primary_currency() {
+            return 0.0;
+        }
+
+        product_type.production_quantity * product_type.value_per_unit / producers.production_time(id) as f64
+    }
+
+    /// The value all owned producers add to the primary currency per millisecond
+    pub fn income_rate(&self) -> f64 {
+        let producers = self.inner.producers();
+
+        producers.ids()
+            .map(|id| producers.count(id) as f64 * self.unit_rate(id))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuum::currency::Cost;
+    use crate::continuum::engine::EngineMode;
+    use crate::continuum::entities::ProductType;
+    use crate::continuum::error::Error;
+
+    fn producer(id: &str, cost: Cost, currency: &str) -> ProducerEntity {
+        ProducerEntity {
+            id: id.to_string(),
+            name: id.to_string(),
+            base_cost: vec![cost],
+            cost_coefficient: 2.0,
+            product_type: ProductType {
+                id: id.to_string(),
+                name: id.to_string(),
+                production_quantity: 1.0,
+                value_per_unit: 2.0,
+                currency: currency.to_string(),
+            },
+            production_time_ms: 1_000,
+            time_elapsed: 0,
+            count: 0,
+        }
+    }
+
+    /// 3 coins to spend on a stall, which makes 2 coins a second, or a lab, which makes research
+    fn simulation() -> Simulation {
+        let config = EngineConfig {
+            mode: EngineMode::SingleThreaded,
+            tick_timeout_ms: 100,
+            starting_funds: 3.0,
+            sell_refund_ratio: 0.5,
+            prestige_bonus: 0.0,
+            seed: 1,
+            world_event_interval_ms: 0,
+        };
+        let currencies = [
+            Currency { id: "coins".to_string(), name: "Coins".to_string() },
+            Currency { id: "research".to_string(), name: "Research".to_string() },
+        ];
+        let producers = [
+            producer("stall", Cost::new("coins", 1.0), "coins"),
+            producer("lab", Cost::new("research", 1.0), "research"),
+        ];
+        Simulation::new(config, &currencies, &producers).unwrap()
+    }
+
+    #[test]
+    fn steps_are_a_tick_long() {
+        let mut sim = simulation();
+
+        for _ in 0..3 {
+            sim.step();
+        }
+        assert_eq!((sim.tick(), sim.elapsed_ms()), (3, 300));
+    }
+
+    #[test]
+    fn purchases_are_paid_for_and_produce() {
+        let mut sim = simulation();
+        let stall = sim.producer_ids()[0];
+
+        sim.purchase(stall).unwrap();
+        assert_eq!(sim.wallet(), 2.0);
+        assert_eq!(sim.cost(stall), 2.0);
+        assert_eq!(sim.income_rate(), 0.002);
+
+        sim.purchase(stall).unwrap();
+        assert!(matches!(sim.purchase(stall), Err(Error::InsufficientFunds { .. })));
+
+        for _ in 0..10 {
+            sim.step();
+        }
+        assert_eq!(sim.wallet(), 4.0);
+    }
+
+    #[test]
+    fn only_the_primary_currency_is_counted() {
+        let sim = simulation();
+        let lab = sim.producer_ids()[1];
+
+        // the lab costs no coins and makes none
+        assert_eq!(sim.cost(lab), 0.0);
+        assert_eq!(sim.unit_rate(lab), 0.0);
+    }
+
+    #[test]
+    fn clones_carry_on_independently() {
+        let mut sim = simulation();
+        let stall = sim.producer_ids()[0];
+        sim.purchase(stall).unwrap();
+        sim.step();
+
+        let (mut original, mut copy) = (sim.clone(), sim);
+        copy.purchase(stall).unwrap();
+        for _ in 0..20 {
+            original.step();
+            copy.step();
+        }
+
+        assert_eq!(original.snapshot().producers[0].count, 1);
+        assert_eq!(copy.snapshot().producers[0].count, 2);
+        assert_eq!(original.wallet(), 6.0);
+        assert_eq!(copy.wallet(), 8.0);
+    }
+}
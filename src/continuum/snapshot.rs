@@ -0,0 +1,114 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::continuum::currency::Price;
+use crate::continuum::entities::{ActiveBuff, Upgrade};
+use crate::continuum::registry::{ActionId, CurrencyId, ProducerId, ProductId};
+use crate::continuum::statistics::Statistics;
+use crate::continuum::world::ActiveOffer;
+
+/// A copy of the engine's state at a moment in time.
+/// Everything is taken under a single lock, so the parts of a snapshot are always consistent with each other
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot {
+    /// The number of ticks the engine has processed
+    pub tick: u64,
+    /// The balance of every currency, ordered by ID
+    pub wallet: Vec<BalanceSnapshot>,
+    /// Every product that has been made, ordered by ID
+    pub products: Vec<ProductSnapshot>,
+    /// Every producer in the engine, ordered by ID
+    pub producers: Vec<ProducerSnapshot>,
+    /// Every manual action, ordered by ID
+    pub actions: Vec<ActionSnapshot>,
+    /// The upgrades applied since the last prestige, in the order they were bought
+    pub upgrades: Vec<Upgrade>,
+    /// The buffs in effect, with the game time each has left
+    pub buffs: Vec<ActiveBuff>,
+    /// The trade offered by the last world event, if it hasn't been taken or run out
+    pub offer: Option<ActiveOffer>,
+    pub statistics: Statistics,
+    pub prestige: u32,
+    pub time_scale: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BalanceSnapshot {
+    pub id: CurrencyId,
+    pub key: String,
+    pub name: String,
+    pub balance: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProductSnapshot {
+    pub id: ProductId,
+    pub key: String,
+    pub name: String,
+    pub quantity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProducerSnapshot {
+    pub id: ProducerId,
+    pub key: String,
+    pub name: String,
+    pub product: ProductId,
+    pub count: u64,
+    /// The cost of the next unit
+    pub cost: Price,
+    pub production_time_ms: u64,
+    /// Progress made towards the current production cycle, in ms
+    pub time_elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ActionSnapshot {
+    pub id: ActionId,
+    pub key: String,
+    pub name: String,
+    pub product: ProductId,
+    /// How much the next use will make, with upgrades and buffs
+    pub next_yield: f64,
+    pub cooldown_ms: u64,
+    /// Game time left before the action can be used again, in ms
+    pub cooldown_remaining_ms: u64,
+    /// How many times the action has been used, and how much it has made altogether
+    pub uses: u64,
+    pub produced: f64,
+}
+
+impl Snapshot {
+    pub fn balance(&self, id: CurrencyId) -> f64 {
+        self.wallet.iter().find(|b| b.id == id).map_or(0.0, |b| b.balance)
+    }
+
+    /// Whether the wallet holds enough of every currency in `price`
+    pub fn can_afford(&self, price: &Price) -> bool {
+        price.iter().all(|(currency, amount)| self.balance(currency) >= amount)
+    }
+
+    pub fn product(&self, id: ProductId) -> Option<&ProductSnapshot> {
+        self.products.iter().find(|p| p.id == id)
+    }
+
+    pub fn producer(&self, id: ProducerId) -> Option<&ProducerSnapshot> {
+        self.producers.iter().find(|p| p.id == id)
+    }
+
+    pub fn action(&self, id: ActionId) -> Option<&ActionSnapshot> {
+        self.actions.iter().find(|a| a.id == id)
+    }
+}
+
+impl ProducerSnapshot {
+    /// How far through its current production cycle the producer is, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        self.time_elapsed_ms as f64 / self.production_time_ms as f64
+    }
+}
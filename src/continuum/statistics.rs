@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::continuum::currency::Price;
+use crate::continuum::error::{Error, Result};
+use crate::continuum::registry::{CurrencyId, ProductId, Registry};
+use crate::continuum::replay::{escape, unescape};
+use crate::logging::{Level, Target};
+
+const HEADER: &str = "statistics 1";
+
+/// Amounts of the primary currency to have earned in a run, whose fastest times are recorded
+pub const MILESTONES: [f64; 6] = [10.0, 100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0];
+
+/// Peak rates are measured over windows of at least this much game time, so that a single cycle
+/// completing doesn't count as a huge rate
+const RATE_WINDOW_MS: u64 = 1000;
+
+/// Totals for a stretch of play
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RunStatistics {
+    /// Game time played, in ms
+    pub time_played_ms: u64,
+    /// How much of each product has been made, by producers and manual actions together
+    pub produced: BTreeMap<ProductId, f64>,
+    /// How much of each product has been made by manual actions
+    pub produced_by_hand: BTreeMap<ProductId, f64>,
+    /// The value of everything made, by currency
+    pub earned: Price,
+    /// What has been paid for producers, upgrades and buffs, by currency
+    pub spent: Price,
+    pub producers_bought: u64,
+    pub actions_performed: u64,
+    /// The most of each currency earned per second
+    pub peak_rate: Price,
+    /// When each of `MILESTONES` was reached, in ms of game time into the run. Over a lifetime this is the fastest run
+    pub milestones_ms: Vec<Option<u64>>,
+}
+
+/// What has happened in the current run (since the last prestige) and over every run.
+///
+/// Statistics are updated as the engine ticks and as things are bought, and can be saved between games.
+/// Only `lifetime` and `prestiges` carry over into a new game, since the run itself isn't saved
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Statistics {
+    pub run: RunStatistics,
+    /// Every run together, including the current one
+    pub lifetime: RunStatistics,
+    pub prestiges: u32,
+    /// Game time and earnings since the last peak rate was measured
+    #[cfg_attr(feature = "serde", serde(skip))]
+    window_ms: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    window_earned: Price,
+}
+
+impl Statistics {
+    /// Records `quantity` of `product` being made, worth `value` of `currency`
+    pub fn produced(&mut self, product: ProductId, quantity: f64, currency: CurrencyId, value: f64) {
+        for stats in [&mut self.run, &mut self.lifetime] {
+            *stats.produced.entry(product).or_insert(0.0) += quantity;
+            stats.earned.add(currency, value);
+        }
+        self.window_earned.add(currency, value);
+    }
+
+    /// Records a use of a manual action that made `quantity` of `product`
+    pub fn performed(&mut self, product: ProductId, quantity: f64) {
+        for stats in [&mut self.run, &mut self.lifetime] {
+            *stats.produced_by_hand.entry(product).or_insert(0.0) += quantity;
+            stats.actions_performed += 1;
+        }
+    }
+
+    pub fn bought(&mut self, count: u64) {
+        self.run.producers_bought += count;
+        self.lifetime.producers_bought += count;
+    }
+
+    pub fn spent(&mut self, price: &Price) {
+        self.run.spent.merge(price);
+        self.lifetime.spent.merge(price);
+    }
+
+    /// Records `elapsed` ms of game time passing, measuring the rate of earnings once enough time has passed
+    /// and recording any milestones that the `primary` currency has reached
+    pub fn pass_time(&mut self, elapsed: u64, primary: Option<CurrencyId>) {
+        self.run.time_played_ms += elapsed;
+        self.lifetime.time_played_ms += elapsed;
+
+        self.window_ms += elapsed;
+        if self.window_ms >= RATE_WINDOW_MS {
+            let rates = self.window_earned.scaled(1000.0 / self.window_ms as f64);
+            for stats in [&mut self.run, &mut self.lifetime] {
+                for (currency, rate) in rates.iter() {
+                    if rate > stats.peak_rate.amount(currency) {
+                        stats.peak_rate.set(currency, rate);
+                    }
+                }
+            }
+            self.window_ms = 0;
+            self.window_earned = Price::default();
+        }
+
+        if let Some(primary) = primary {
+            let earned = self.run.earned.amount(primary);
+            let reached = MILESTONES.iter().take_while(|target| earned >= **target).count();
+            let at = self.run.time_played_ms;
+
+            for stats in [&mut self.run, &mut self.lifetime] {
+                stats.milestones_ms.resize(MILESTONES.len(), None);
+            }
+            for i in 0..reached {
+                if self.run.milestones_ms[i].is_none() {
+                    self.run.milestones_ms[i] = Some(at);
+                    let fastest = &mut self.lifetime.milestones_ms[i];
+                    *fastest = Some(fastest.map_or(at, |ms| ms.min(at)));
+                }
+            }
+        }
+    }
+
+    /// Starts a new run after a prestige, keeping the lifetime totals
+    pub fn start_run(&mut self) {
+        self.run = RunStatistics::default();
+        self.prestiges += 1;
+    }
+
+    /// Keeps the lifetime totals and prestige count of `saved`, e.g. from a previous game, in place of these
+    pub fn restore(&mut self, saved: Statistics) {
+        self.lifetime = saved.lifetime;
+        self.prestiges = saved.prestiges;
+    }
+
+    /// Writes the statistics to `path`. Products and currencies are written by key, looked up in `registry`
+    pub fn save<P: AsRef<Path>>(&self, path: P, registry: &Registry) -> Result<()> {
+        fs::write(path, self.to_text(registry)).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    /// Reads statistics written by `save()`, looking keys up in `registry`
+    pub fn load<P: AsRef<Path>>(path: P, registry: &Registry) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+        Self::parse(&text, registry)
+    }
+
+    /// `prestiges\t<count>`, then one line per total as `<run|lifetime>\t<statistic>\t<arguments...>`.
+    /// Keys are escaped as in replays, so they can contain the separators
+    pub fn to_text(&self, registry: &Registry) -> String {
+        let mut text = format!("{}\nprestiges\t{}\n", HEADER, self.prestiges);
+
+        for (scope, stats) in [("run", &self.run), ("lifetime", &self.lifetime)] {
+            let _ = writeln!(text, "{}\ttime\t{}", scope, stats.time_played_ms);
+            let _ = writeln!(text, "{}\tbought\t{}", scope, stats.producers_bought);
+            let _ = writeln!(text, "{}\tactions\t{}", scope, stats.actions_performed);
+            for (name, products) in [("produced", &stats.produced), ("by_hand", &stats.produced_by_hand)] {
+                for (product, quantity) in products {
+                    let _ = writeln!(text, "{}\t{}\t{}\t{}", scope, name, escape(registry.product_key(*product)), quantity);
+                }
+            }
+            for (name, price) in [("earned", &stats.earned), ("spent", &stats.spent), ("peak", &stats.peak_rate)] {
+                for (currency, amount) in price.iter() {
+                    let _ = writeln!(text, "{}\t{}\t{}\t{}", scope, name, escape(registry.currency_key(currency)), amount);
+                }
+            }
+            for (i, ms) in stats.milestones_ms.iter().enumerate() {
+                if let Some(ms) = ms {
+                    let _ = writeln!(text, "{}\tmilestone\t{}\t{}", scope, i, ms);
+                }
+            }
+        }
+        text
+    }
+
+    /// Reads statistics written by `to_text()`. Totals for products or currencies that no longer exist are skipped
+    pub fn parse(text: &str, registry: &Registry) -> Result<Self> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(invalid(1, format!("expected '{}'", HEADER))),
+        }
+
+        let mut statistics = Statistics::default();
+        for (number, line) in lines {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let field = |index: usize| fields.get(index).copied().ok_or_else(|| invalid(number, format!("missing field {}", index)));
+            let parse_u64 = |index: usize| field(index)?.parse::<u64>().map_err(|_| invalid(number, format!("'{}' is not a number", fields[index])));
+            let parse_f64 = |index: usize| field(index)?.parse::<f64>().map_err(|_| invalid(number, format!("'{}' is not a number", fields[index])));
+            let key = |index: usize| unescape(field(index)?).ok_or_else(|| invalid(number, format!("'{}' is badly escaped", fields[index])));
+
+            if fields[0] == "prestiges" {
+                statistics.prestiges = field(1)?.parse::<u32>().map_err(|_| invalid(number, format!("'{}' is not a prestige count", fields[1])))?;
+                continue;
+            }
+            let stats = match fields[0] {
+                "run" => &mut statistics.run,
+                "lifetime" => &mut statistics.lifetime,
+                other => return Err(invalid(number, format!("unknown scope '{}'", other))),
+            };
+
+            match field(1)? {
+                "time" => stats.time_played_ms = parse_u64(2)?,
+                "bought" => stats.producers_bought = parse_u64(2)?,
+                "actions" => stats.actions_performed = parse_u64(2)?,
+                name @ ("produced" | "by_hand") => match registry.product(&key(2)?) {
+                    Some(product) => {
+                        let products = if name == "produced" { &mut stats.produced } else { &mut stats.produced_by_hand };
+                        products.insert(product, parse_f64(3)?);
+                    }
+                    None => log!(Level::Warn, Target::Engine, "skipping statistics for unknown product {}", fields[2]),
+                },
+                name @ ("earned" | "spent" | "peak") => match registry.currency(&key(2)?) {
+                    Some(currency) => {
+                        let price = match name {
+                            "earned" => &mut stats.earned,
+                            "spent" => &mut stats.spent,
+                            _ => &mut stats.peak_rate,
+                        };
+                        price.add(currency, parse_f64(3)?);
+                    }
+                    None => log!(Level::Warn, Target::Engine, "skipping statistics for unknown currency {}", fields[2]),
+                },
+                "milestone" => {
+                    let index = parse_u64(2)? as usize;
+                    if index < MILESTONES.len() {
+                        stats.milestones_ms.resize(MILESTONES.len(), None);
+                        stats.milestones_ms[index] = Some(parse_u64(3)?);
+                    }
+                }
+                other => return Err(invalid(number, format!("unknown statistic '{}'", other))),
+            }
+        }
+        Ok(statistics)
+    }
+}
+
+fn invalid(line: usize, reason: String) -> Error {
+    Error::InvalidSave { line, reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_survive_a_round_trip() {
+        let mut registry = Registry::default();
+        let coins = registry.intern_currency("coins=1,2%", "Coins");
+        let ore = registry.intern_product("gold\tore\n", "Gold ore");
+        let mut price = Price::default();
+        price.add(coins, 1.5);
+
+        let mut statistics = Statistics::default();
+        statistics.produced(ore, 3.5, coins, 7.25);
+        statistics.performed(ore, 0.5);
+        statistics.bought(2);
+        statistics.spent(&price);
+        statistics.pass_time(1_000, Some(coins));
+        statistics.start_run();
+        statistics.produced(ore, 10.0, coins, 20.0);
+        statistics.pass_time(2_000, Some(coins));
+
+        let text = statistics.to_text(&registry);
+        assert_eq!(Statistics::parse(&text, &registry), Ok(statistics));
+    }
+
+    #[test]
+    fn prestige_counts_must_fit() {
+        let registry = Registry::default();
+        let text = format!("{}\nprestiges\t{}\n", HEADER, u64::from(u32::MAX) + 1);
+
+        assert!(matches!(Statistics::parse(&text, &registry), Err(Error::InvalidSave { line: 2, .. })));
+    }
+}
@@ -0,0 +1,280 @@
+use crate::continuum::currency::Cost;
+use crate::continuum::entities::{run_cycles, scale_costs, Producer, ProducerEntity, ProductType};
+use crate::continuum::registry::{ProducerId, ProductId};
+
+/// Where the data for a `ProducerId` lives
+#[derive(Debug, Copy, Clone)]
+enum Slot {
+    /// An index into the component vectors of `ProducerStorage`
+    Entity(usize),
+    /// An index into `ProducerStorage::custom`
+    Custom(usize),
+}
+
+/// Contiguous storage for every producer known to the engine, indexed by `ProducerId`.
+///
+/// Plain `ProducerEntity` producers are split into one vector per component so that a tick is a
+/// single pass over tightly packed data. Producers with their own behaviour are kept as
+/// `Box<dyn Producer>` and ticked through the trait.
+#[derive(Debug, Clone, Default)]
+pub struct ProducerStorage {
+    slots: Vec<Option<Slot>>,
+
+    // `ProducerEntity` components
+    entity_ids: Vec<ProducerId>,
+    product_ids: Vec<ProductId>,
+    base_costs: Vec<Vec<Cost>>,
+    cost_coefficients: Vec<f64>,
+    product_types: Vec<ProductType>,
+    production_times: Vec<u64>,
+    time_elapsed: Vec<u64>,
+    counts: Vec<u64>,
+
+    custom_ids: Vec<ProducerId>,
+    custom_product_ids: Vec<ProductId>,
+    custom: Vec<Box<dyn Producer>>,
+}
+
+impl ProducerStorage {
+    pub fn insert_entity(&mut self, id: ProducerId, product: ProductId, producer: ProducerEntity) {
+        self.set_slot(id, Slot::Entity(self.counts.len()));
+
+        self.entity_ids.push(id);
+        self.product_ids.push(product);
+        self.base_costs.push(producer.base_cost);
+        self.cost_coefficients.push(producer.cost_coefficient);
+        self.product_types.push(producer.product_type);
+        self.production_times.push(producer.production_time_ms);
+        self.time_elapsed.push(producer.time_elapsed);
+        self.counts.push(producer.count);
+    }
+
+    pub fn insert_custom(&mut self, id: ProducerId, product: ProductId, producer: Box<dyn Producer>) {
+        self.set_slot(id, Slot::Custom(self.custom.len()));
+
+        self.custom_ids.push(id);
+        self.custom_product_ids.push(product);
+        self.custom.push(producer);
+    }
+
+    /// Removes the producer `id`. Its ID is not reused
+    pub fn remove(&mut self, id: ProducerId) {
+        let slot = match self.slots.get_mut(id.index()).and_then(|s| s.take()) {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        // the last producer of the same kind is moved into the freed space, so its slot has to follow it
+        match slot {
+            Slot::Entity(i) => {
+                self.entity_ids.swap_remove(i);
+                self.product_ids.swap_remove(i);
+                self.base_costs.swap_remove(i);
+                self.cost_coefficients.swap_remove(i);
+                self.product_types.swap_remove(i);
+                self.production_times.swap_remove(i);
+                self.time_elapsed.swap_remove(i);
+                self.counts.swap_remove(i);
+
+                if let Some(moved) = self.entity_ids.get(i) {
+                    self.slots[moved.index()] = Some(Slot::Entity(i));
+                }
+            }
+            Slot::Custom(i) => {
+                self.custom_ids.swap_remove(i);
+                self.custom_product_ids.swap_remove(i);
+                self.custom.swap_remove(i);
+
+                if let Some(moved) = self.custom_ids.get(i) {
+                    self.slots[moved.index()] = Some(Slot::Custom(i));
+                }
+            }
+        }
+    }
+
+    fn set_slot(&mut self, id: ProducerId, slot: Slot) {
+        if self.slots.len() <= id.index() {
+            self.slots.resize(id.index() + 1, None);
+        }
+        self.slots[id.index()] = Some(slot);
+    }
+
+    fn slot(&self, id: ProducerId) -> Slot {
+        self.slots[id.index()].expect("no producer stored for ProducerId")
+    }
+
+    pub fn contains(&self, id: ProducerId) -> bool {
+        self.slots.get(id.index()).is_some_and(|s| s.is_some())
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = ProducerId> + '_ {
+        self.entity_ids.iter().chain(self.custom_ids.iter()).copied()
+    }
+
+    pub fn product(&self, id: ProducerId) -> ProductId {
+        match self.slot(id) {
+            Slot::Entity(i) => self.product_ids[i],
+            Slot::Custom(i) => self.custom_product_ids[i],
+        }
+    }
+
+    pub fn product_type(&self, id: ProducerId) -> &ProductType {
+        match self.slot(id) {
+            Slot::Entity(i) => &self.product_types[i],
+            Slot::Custom(i) => self.custom[i].product_type(),
+        }
+    }
+
+    pub fn production_time(&self, id: ProducerId) -> u64 {
+        match self.slot(id) {
+            Slot::Entity(i) => self.production_times[i],
+            Slot::Custom(i) => self.custom[i].production_time(),
+        }
+    }
+
+    pub fn count(&self, id: ProducerId) -> u64 {
+        match self.slot(id) {
+            Slot::Entity(i) => self.counts[i],
+            Slot::Custom(i) => self.custom[i].count(),
+        }
+    }
+
+    pub fn set_count(&mut self, id: ProducerId, count: u64) {
+        match self.slot(id) {
+            Slot::Entity(i) => self.counts[i] = count,
+            Slot::Custom(i) => self.custom[i].set_count(count),
+        }
+    }
+
+    /// Progress made towards the producer's current production cycle, in ms
+    pub fn time_elapsed(&self, id: ProducerId) -> u64 {
+        match self.slot(id) {
+            Slot::Entity(i) => self.time_elapsed[i],
+            Slot::Custom(i) => self.custom[i].time_elapsed(),
+        }
+    }
+
+    pub fn set_time_elapsed(&mut self, id: ProducerId, t: u64) {
+        match self.slot(id) {
+            Slot::Entity(i) => self.time_elapsed[i] = t,
+            Slot::Custom(i) => self.custom[i].set_time_elapsed(t),
+        }
+    }
+
+    /// The price of the unit numbered `index` of the producer (the first unit bought is 0)
+    pub fn unit_cost(&self, id: ProducerId, index: u64) -> Vec<Cost> {
+        match self.slot(id) {
+            Slot::Entity(i) => scale_costs(&self.base_costs[i], self.cost_coefficients[i].powi(index as i32)),
+            Slot::Custom(i) => self.custom[i].unit_cost(index),
+        }
+    }
+
+    /// The cost of purchasing the next unit of the producer
+    pub fn cost(&self, id: ProducerId) -> Vec<Cost> {
+        self.unit_cost(id, self.count(id))
+    }
+
+    pub fn any_inputs(&self) -> bool {
+        self.custom.iter().any(|p| p.has_inputs())
+    }
+
+    /// Progress every producer by the ms that `elapsed` gives for it, calling `sink`
+    /// with the quantity made by each producer that completed at least one cycle
+    pub fn tick<E, F>(&mut self, mut elapsed: E, mut sink: F)
+    where
+        E: FnMut(ProducerId) -> u64,
+        F: FnMut(ProducerId, ProductId, &ProductType, f64),
+    {
+        // what `ProducerEntity::on_tick()` does, over packed components
+        for i in 0..self.counts.len() {
+            if self.counts[i] == 0 {
+                continue;
+            }
+
+            let cycles = run_cycles(&mut self.time_elapsed[i], elapsed(self.entity_ids[i]), self.production_times[i]);
+
+            if cycles > 0 {
+                let product_type = &self.product_types[i];
+                sink(self.entity_ids[i], self.product_ids[i], product_type, cycles as f64 * product_type.production_quantity * self.counts[i] as f64);
+            }
+        }
+
+        for (i, producer) in self.custom.iter_mut().enumerate() {
+            let q = producer.on_tick(elapsed(self.custom_ids[i]));
+            if q > 0.0 {
+                sink(self.custom_ids[i], self.custom_product_ids[i], producer.product_type(), q);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuum::registry::Registry;
+
+    fn entity(n: u64) -> ProducerEntity {
+        ProducerEntity {
+            id: format!("producer-{}", n),
+            name: format!("Producer {}", n),
+            base_cost: vec![Cost::new("coins", 1.0)],
+            cost_coefficient: 1.1,
+            product_type: ProductType {
+                id: "gold".to_string(),
+                name: "Gold".to_string(),
+                production_quantity: 1.0,
+                value_per_unit: 1.0,
+                currency: "coins".to_string(),
+            },
+            production_time_ms: 100 * (n + 1),
+            time_elapsed: 10 * n,
+            count: n + 1,
+        }
+    }
+
+    /// Three producers, stored as entities or as custom producers, where producer `n` owns `n + 1` units
+    fn storage(custom: bool) -> (ProducerStorage, Vec<ProducerId>) {
+        let mut registry = Registry::default();
+        let mut storage = ProducerStorage::default();
+        let product = registry.intern_product("gold", "Gold");
+
+        let ids = (0..3).map(|n| {
+            let producer = entity(n);
+            let id = registry.intern_producer(&producer.id, &producer.name);
+            if custom {
+                storage.insert_custom(id, product, Box::new(producer));
+            } else {
+                storage.insert_entity(id, product, producer);
+            }
+            id
+        }).collect();
+        (storage, ids)
+    }
+
+    #[test]
+    fn removing_any_producer_keeps_the_others_in_their_slots() {
+        for custom in [false, true] {
+            // the first, the middle and the last producer
+            for removed in 0..3 {
+                let (mut storage, ids) = storage(custom);
+
+                storage.remove(ids[removed]);
+                assert!(!storage.contains(ids[removed]));
+                assert_eq!(storage.ids().count(), 2);
+
+                for (n, id) in ids.iter().enumerate().filter(|(n, _)| *n != removed) {
+                    let n = n as u64;
+                    assert!(storage.contains(*id));
+                    assert_eq!(storage.count(*id), n + 1);
+                    assert_eq!(storage.time_elapsed(*id), 10 * n);
+                    assert_eq!(storage.production_time(*id), 100 * (n + 1));
+                    assert_eq!(storage.cost(*id), scale_costs(&[Cost::new("coins", 1.0)], 1.1f64.powi(n as i32 + 1)));
+                }
+
+                // removing it again does nothing
+                storage.remove(ids[removed]);
+                assert_eq!(storage.ids().count(), 2);
+            }
+        }
+    }
+}
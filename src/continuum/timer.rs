@@ -1,11 +1,14 @@
 use std::sync::mpsc::{channel, Receiver};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
+
+use crate::continuum::error::{lock, Error, Result};
+use crate::logging::{Level, Target};
 
 #[derive(Debug)]
 pub struct Timer {
-    thread_handle: Option<thread::JoinHandle<()>>,
+    thread_handle: Option<thread::JoinHandle<Result<()>>>,
     state: Arc<Mutex<TimerState>>,
     timeout_ms: u64,
 }
@@ -25,56 +28,121 @@ impl Timer {
         }
     }
 
-    pub fn start(&mut self) -> Receiver<u64> {
+    /// Starts the timer thread, returning the receiving end of the channel it sends ticks to.
+    /// A timer can be started again after it has been stopped, and each start creates a new channel.
+    /// Unlike stopping, starting is not idempotent: while the timer is running its ticks already go to the receiver
+    /// returned by the first start, so starting it again fails with `Error::AlreadyRunning`
+    pub fn start(&mut self) -> Result<Receiver<u64>> {
+        // the thread stops by itself if its receiver is dropped, in which case it only needs to be joined
+        if self.thread_handle.as_ref().is_some_and(|h| h.is_finished()) {
+            self.stop()?;
+        }
+        if self.thread_handle.is_some() {
+            return Err(Error::AlreadyRunning);
+        }
+
         let thread_state = self.state.clone();
         let (sender, receiver) = channel();
         let thread_timeout = self.timeout_ms;
 
-        self.set_state(TimerState::Running);
+        self.set_state(TimerState::Running)?;
+        log!(Level::Debug, Target::Timer, "timer starting with a {}ms timeout", thread_timeout);
 
         self.thread_handle = Some(thread::spawn(move || {
             loop {
-                let state = { *thread_state.lock().unwrap() };
+                let state = { *lock(&thread_state, "timer state")? };
 
                 if state == TimerState::Running {
-                    let now = SystemTime::now();
+                    let now = Instant::now();
 
                     thread::sleep(Duration::from_millis(thread_timeout));
-                    match sender.send(now.elapsed().unwrap().as_millis() as u64) {
-                        Ok(_) => {},
-                        Err(e) => {
-                            println!("Send ERROR: {}", e);
-                            *thread_state.lock().unwrap() = TimerState::Stopped;
-                        }
+                    if sender.send(now.elapsed().as_millis() as u64).is_err() {
+                        // nobody is listening for ticks any more
+                        log!(Level::Warn, Target::Timer, "tick receiver dropped, stopping timer");
+                        *lock(&thread_state, "timer state")? = TimerState::Stopped;
                     }
                 } else {
-                    break;
+                    break Ok(());
                 }
             }
         }));
 
-        receiver
+        Ok(receiver)
     }
 
-    pub fn stop(&mut self) {
+    /// Stops the timer thread and waits for it to finish. Stopping a timer that isn't running does nothing
+    pub fn stop(&mut self) -> Result<()> {
         let handle = self.thread_handle.take();
 
-        self.set_state(TimerState::Stopped);
+        self.set_state(TimerState::Stopped)?;
 
-        match handle {
-            Some(h) => match h.join() {
-                Err(e) => println!("JOIN Error: {:?}", e),
-                Ok(_) => println!("Timer thread joined"),
-            },
-            None => println!("Timer.stop() called but thread_handle == None!"),
+        if let Some(h) = handle {
+            h.join().map_err(|_| Error::ThreadPanicked("timer"))??;
+            log!(Level::Debug, Target::Timer, "timer thread joined");
         }
+        Ok(())
+    }
+
+    pub fn state(&self) -> Result<TimerState> {
+        Ok(*lock(&self.state, "timer state")?)
     }
 
-    pub fn state(&self) -> TimerState {
-        *self.state.lock().unwrap()
+    fn set_state(&mut self, state: TimerState) -> Result<()> {
+        *lock(&self.state, "timer state")? = state;
+        Ok(())
     }
+}
 
-    fn set_state(&mut self, state: TimerState) {
-        *self.state.lock().unwrap() = state
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Err(e) = self.stop() {
+            log!(Level::Warn, Target::Timer, "failed to stop timer while dropping it: {}", e);
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarts_after_stopping() {
+        let mut timer = Timer::new(1);
+
+        for _ in 0..3 {
+            let receiver = timer.start().unwrap();
+            assert_eq!(timer.state(), Ok(TimerState::Running));
+            receiver.recv().unwrap();
+
+            timer.stop().unwrap();
+            assert_eq!(timer.state(), Ok(TimerState::Stopped));
+            // the thread has been joined, so it has dropped its end of the channel
+            while receiver.recv().is_ok() {}
+        }
+    }
+
+    #[test]
+    fn starting_twice_fails_and_stopping_twice_does_nothing() {
+        let mut timer = Timer::new(1);
+
+        let _receiver = timer.start().unwrap();
+        assert!(matches!(timer.start(), Err(Error::AlreadyRunning)));
+        assert_eq!(timer.state(), Ok(TimerState::Running));
+
+        timer.stop().unwrap();
+        timer.stop().unwrap();
+        assert_eq!(timer.state(), Ok(TimerState::Stopped));
+    }
+
+    #[test]
+    fn dropping_a_running_timer_joins_its_thread() {
+        let mut timer = Timer::new(1);
+        let receiver = timer.start().unwrap();
+        receiver.recv().unwrap();
+
+        // the timer thread holds the only other reference to the state, until it has finished
+        let state = Arc::downgrade(&timer.state);
+        drop(timer);
+        assert!(state.upgrade().is_none());
+    }
+}
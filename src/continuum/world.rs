@@ -0,0 +1,54 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::continuum::currency::Cost;
+
+/// Something that happens to the world at random, drawn from the events added with `Engine::add_world_event()`
+/// roughly every `EngineConfig::world_event_interval_ms`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WorldEvent {
+    /// The stable key the event is known by
+    pub id: String,
+    /// The name shown to the player
+    pub name: String,
+    /// What the player is told when the event happens
+    pub description: String,
+    /// How likely the event is to be drawn, relative to the weights of the other events
+    pub weight: u32,
+    pub effect: WorldEffect,
+}
+
+/// What a `WorldEvent` does when it happens. Products and currencies are referred to by key, so that events
+/// can be defined alongside the producers that make them
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WorldEffect {
+    /// Multiplies how much of the product `product` is made for `duration_ms`, as a buff named after the event
+    Boost { product: String, multiplier: f64, duration_ms: u64 },
+    /// Destroys `fraction` of the stock of the product `product`
+    Destroy { product: String, fraction: f64 },
+    /// Offers a trade, which the player can take with `EngineCommand::AcceptOffer` until it runs out
+    Offer(Offer),
+}
+
+/// A trade of one set of currencies for another
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Offer {
+    pub give: Vec<Cost>,
+    pub receive: Vec<Cost>,
+    /// How long the offer stands for, in ms of game time
+    pub duration_ms: u64,
+}
+
+/// The offer currently on the table
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ActiveOffer {
+    /// The name of the event that made the offer
+    pub name: String,
+    pub offer: Offer,
+    /// Game time left before the offer is withdrawn, in ms
+    pub remaining_ms: u64,
+}
@@ -1,6 +1,6 @@
 use tui::buffer::Buffer;
 use tui::layout::Rect;
-use tui::style::{Style, Color};
+use tui::style::Style;
 use tui::widgets::{Widget, Block, Borders};
 
 ///
@@ -8,7 +8,7 @@ use tui::widgets::{Widget, Block, Borders};
 /// 
 /// # Example
 /// 
-/// ```
+/// ```ignore
 /// terminal.draw(|mut f| {
 ///     let size = f.size();
 ///     Label::default().text("Test").render(&mut f, size);
@@ -18,6 +18,7 @@ pub struct Label<'a> {
     text: &'a str,
 }
 
+#[allow(clippy::derivable_impls)]
 impl<'a> Default for Label<'a> {
     fn default() -> Self {
         Label { text: "" }
@@ -46,6 +47,7 @@ pub struct Button<'a> {
     text: &'a str,
 }
 
+#[allow(clippy::derivable_impls)]
 impl<'a> Default for Button<'a> {
     fn default() -> Self {
         Button { 
@@ -69,7 +71,9 @@ impl<'a> Widget for Button<'a> {
 
         if text_area.height < 1 { return };
 
-        buf.set_string(text_area.left(), text_area.top(), format!("{:^width$}", self.text, width=text_area.width as usize), self.style);
+        // text too long for the button is cut off rather than drawn over its border
+        let width = text_area.width as usize;
+        buf.set_stringn(text_area.left(), text_area.top(), format!("{:^width$}", self.text, width=width), width, self.style);
     }
 }
 
@@ -83,4 +87,134 @@ impl<'a> Button<'a> {
         self.style = style;
         self
     }
-}
\ No newline at end of file
+}
+///
+/// A box drawn over the top of whatever is beneath it, showing a page of text at a time
+/// 
+/// Lines too long for the box are wrapped at spaces. The last line of the box shows which page is shown, if there is
+/// more than one, followed by the footer, e.g. to say which keys turn the page
+/// 
+/// # Example
+/// 
+/// ```ignore
+/// terminal.draw(|mut f| {
+///     let size = f.size();
+///     Modal::default().title("[ Help ]").lines(&lines).page(1).render(&mut f, size);
+/// })?;
+/// ```
+#[derive(Default)]
+pub struct Modal<'a> {
+    title: &'a str,
+    lines: &'a [String],
+    footer: &'a str,
+    page: usize,
+    style: Style,
+}
+
+impl<'a> Widget for Modal<'a> {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        // nothing beneath the modal shows through
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf.get_mut(x, y).reset();
+            }
+        }
+
+        let text_area = {
+            let mut block = Block::default()
+                .borders(Borders::ALL)
+                .title(self.title)
+                .border_style(self.style)
+                .style(self.style);
+
+            block.draw(area, buf);
+            block.inner(area)
+        };
+
+        let page_height = Self::page_height(text_area);
+        if page_height < 1 { return };
+
+        let lines = self.wrapped(text_area.width as usize);
+        let pages = lines.len().div_ceil(page_height).max(1);
+        let page = self.page.min(pages - 1);
+
+        for (y, line) in (text_area.top()..).zip(lines.iter().skip(page * page_height).take(page_height)) {
+            buf.set_stringn(text_area.left(), y, line, text_area.width as usize, self.style);
+        }
+        let footer = match pages {
+            1 => self.footer.to_string(),
+            _ => format!("Page {} of {} - {}", page + 1, pages, self.footer),
+        };
+        buf.set_stringn(text_area.left(), text_area.bottom() - 1, footer, text_area.width as usize, self.style);
+    }
+}
+
+impl<'a> Modal<'a> {
+    pub fn title(&mut self, title: &'a str) -> &mut Self {
+        self.title = title;
+        self
+    }
+
+    pub fn lines(&mut self, lines: &'a [String]) -> &mut Self {
+        self.lines = lines;
+        self
+    }
+
+    pub fn footer(&mut self, footer: &'a str) -> &mut Self {
+        self.footer = footer;
+        self
+    }
+
+    /// The page to show, counting from 0. Pages past the last show the last page
+    pub fn page(&mut self, page: usize) -> &mut Self {
+        self.page = page;
+        self
+    }
+
+    pub fn style(&mut self, style: Style) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    /// How many pages the lines take up when the modal is drawn in `area`
+    pub fn page_count(&self, area: Rect) -> usize {
+        let text_area = Block::default().borders(Borders::ALL).inner(area);
+        match Self::page_height(text_area) {
+            0 => 1,
+            height => self.wrapped(text_area.width as usize).len().div_ceil(height).max(1),
+        }
+    }
+
+    /// The lines of text that fit on a page, leaving a blank line and the footer below them
+    fn page_height(text_area: Rect) -> usize {
+        text_area.height.saturating_sub(2) as usize
+    }
+
+    /// The lines broken at spaces so that none is wider than `width`, where possible. Lines that already fit are
+    /// left as they are, spacing and all
+    fn wrapped(&self, width: usize) -> Vec<String> {
+        let mut wrapped = Vec::new();
+        for line in self.lines {
+            if line.chars().count() <= width {
+                wrapped.push(line.clone());
+                continue;
+            }
+            // lines keep their indentation when they wrap
+            let indent = line.len() - line.trim_start().len();
+            let mut current = line[..indent].to_string();
+            for word in line.split_whitespace() {
+                if current.trim_start().is_empty() || current.chars().count() + 1 + word.chars().count() <= width {
+                    if !current.trim_start().is_empty() {
+                        current.push(' ');
+                    }
+                    current.push_str(word);
+                } else {
+                    wrapped.push(current);
+                    current = format!("{}{}", &line[..indent], word);
+                }
+            }
+            wrapped.push(current);
+        }
+        wrapped
+    }
+}
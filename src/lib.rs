@@ -0,0 +1,6 @@
+#[macro_use]
+pub mod logging;
+pub mod continuum;
+pub mod content;
+pub mod custom_widgets;
+pub mod ui;
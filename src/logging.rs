@@ -0,0 +1,173 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How important a log message is. Messages less important than the current level are dropped
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn from_u8(level: u8) -> Level {
+        match level {
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+
+    /// The next more verbose level, wrapping back around to `Error` after `Trace`
+    pub fn next(self) -> Level {
+        match self {
+            Level::Trace => Level::Error,
+            level => Level::from_u8(level as u8 + 1),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The part of the game a log message came from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Engine,
+    Timer,
+    Ui,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Target::Engine => "engine",
+            Target::Timer => "timer",
+            Target::Ui => "ui",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A log file that is rotated once it grows past `max_bytes`, keeping up to `max_files` old files
+/// alongside it as `<path>.1` (the newest) to `<path>.<max_files>` (the oldest)
+#[derive(Debug)]
+struct FileSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl FileSink {
+    fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+
+        Ok(FileSink {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.written + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let numbered = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+
+        for n in (1..self.max_files).rev() {
+            if numbered(n).exists() {
+                fs::rename(numbered(n), numbered(n + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            fs::rename(&self.path, numbered(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static SINK: Mutex<Option<FileSink>> = Mutex::new(None);
+
+/// Starts writing log messages to the file at `path`. Until this is called, messages are dropped.
+/// stdout can't be used for logging as it belongs to the UI
+pub fn init<P: AsRef<Path>>(path: P, max_bytes: u64, max_files: usize) -> io::Result<()> {
+    let sink = FileSink::open(path.as_ref(), max_bytes, max_files)?;
+
+    if let Ok(mut s) = SINK.lock() {
+        *s = Some(sink);
+    }
+    Ok(())
+}
+
+/// Changes which messages are logged. Can be called at any time, from any thread
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    Level::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Writes a message to the log file. Use the `log!` macro rather than calling this directly,
+/// so that messages below the current level aren't formatted
+pub fn write(level: Level, target: Target, args: fmt::Arguments) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let line = format!("{}.{:03} {:<5} [{}] {}\n", timestamp.as_secs(), timestamp.subsec_millis(), level, target, args);
+
+    // there's nowhere to report a failure to log, so failures are ignored
+    if let Ok(mut sink) = SINK.lock() {
+        if let Some(sink) = sink.as_mut() {
+            let _ = sink.write_line(&line);
+        }
+    }
+}
+
+/// Logs a message if `$level` is enabled, e.g. `log!(Level::Debug, Target::Engine, "tick {}", elapsed)`
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $target:expr, $($arg:tt)+) => {
+        if $crate::logging::enabled($level) {
+            $crate::logging::write($level, $target, format_args!($($arg)+));
+        }
+    };
+}
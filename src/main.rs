@@ -1,40 +1,195 @@
-mod continuum;
-mod custom_widgets;
-mod ui;
-
-use continuum::{Engine, EngineConfig, ProducerEntity, ProductType};
+use incremental_game_test::{content, continuum, log, logging, ui};
+use continuum::{Buff, BuffEffect, Cost, CurrencyId, Engine, EngineConfig, EngineMode, Optimizer, PendingCommand, ProducerEntity, ProducerId, ProductType, Replay, Simulation, Statistics, Strategy};
 use std::error::Error;
-use std::io::{self, Write};
-use ui::{Event, KeyCode, UI};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use logging::{Level, Target};
+use ui::{Action, Event, FrameScheduler, HelpScreen, KeyBindings, ProducersScreen, StatsScreen, UI};
+
+/// Where the commands of the last game played are saved, for `replay`
+const REPLAY_PATH: &str = "idle-terminal.replay";
+/// Where statistics are saved between games
+const STATISTICS_PATH: &str = "idle-terminal.stats";
+/// Where players can change which keys do what (see `KeyBindings`)
+const BINDINGS_PATH: &str = "idle-terminal.keys";
+/// Where the details of a panic are written
+const CRASH_REPORT_PATH: &str = "idle-terminal.crash";
+/// How many frames a second are drawn, unless set with `--fps`
+const DEFAULT_FPS: u32 = 30;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut engine = Engine::new(EngineConfig {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    // the engine runs on the UI loop unless asked to run on its own threads
+    let mode = if args.iter().any(|a| a == "--threaded") {
+        EngineMode::Threaded
+    } else {
+        EngineMode::SingleThreaded
+    };
+    let config = EngineConfig {
+        mode,
         tick_timeout_ms: 50,
-    });
+        starting_funds: 1.0,
+        sell_refund_ratio: 0.5,
+        prestige_bonus: 0.1,
+        // every game plays out differently, and the seed is saved with the replay so it can be played back
+        seed: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64),
+        world_event_interval_ms: 90_000,
+    };
+
+    if args.get(1).map(|a| a.as_str()) == Some("optimize") {
+        let target = args.get(2).and_then(|t| t.parse().ok()).unwrap_or(1000.0);
+        run_optimizer(config, target)?;
+        return Ok(());
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("bench") {
+        let count = args.get(2).and_then(|c| c.parse().ok()).unwrap_or(10_000);
+        run_benchmark(config, count)?;
+        return Ok(());
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("replay") {
+        let path = args.get(2).map(|p| p.as_str()).unwrap_or(REPLAY_PATH);
+        run_replay(config, path)?;
+        return Ok(());
+    }
+
+    // stdout belongs to the UI, so diagnostics go to a log file instead
+    logging::init("idle-terminal.log", 1024 * 1024, 3)?;
 
-    setup_producers(&mut engine);
-    engine.start();
+    let mut engine = Engine::new(config)?;
 
+    let currencies = setup_currencies(&mut engine)?;
+    setup_producers(&mut engine)?;
+    for action in content::actions() {
+        engine.add_action(action)?;
+    }
+    for event in content::world_events() {
+        engine.add_world_event(event)?;
+    }
+    let wood = engine.product_id("wood")?.expect("wood is made by a producer added by setup_producers()");
+    let frenzy = Buff {
+        id: "frenzy".to_string(),
+        name: "Wood Frenzy".to_string(),
+        effect: BuffEffect::Output { product: wood, multiplier: 2.0 },
+        duration_ms: 30_000,
+        cost: vec![Cost::new("coins", 10.0)],
+    };
+    engine.record()?;
+    engine.start()?;
+
+    ui::install_panic_hook(CRASH_REPORT_PATH);
     let mut ui = UI::new().unwrap();
+    ui.watch(engine.subscribe()?);
+    ui.add_screen(Box::new(ProducersScreen::new(frenzy, (currencies[0], currencies[1]))));
+    ui.add_screen(Box::new(StatsScreen));
+    ui.add_screen(Box::new(HelpScreen));
+
+    if Path::new(BINDINGS_PATH).exists() {
+        match KeyBindings::load(BINDINGS_PATH) {
+            Ok(bindings) => ui.set_bindings(bindings),
+            Err(e) => ui.show_message(format!("Error: {} - using the default keys", e)),
+        }
+    }
+
+    // lifetime statistics carry over from earlier games
+    if Path::new(STATISTICS_PATH).exists() {
+        match Statistics::load(STATISTICS_PATH, &engine.registry()?) {
+            Ok(statistics) => engine.restore_statistics(statistics)?,
+            Err(e) => ui.report_error(&e),
+        }
+    }
+
+    // ctrl+c arrives as a key press while the terminal is in raw mode, but the same signals sent from elsewhere
+    // ask whether to save too
+    let quit_signal = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::SIGINT, Arc::clone(&quit_signal))?;
+    signal_hook::flag::register(signal_hook::SIGTERM, Arc::clone(&quit_signal))?;
+
+    // commands that have been sent to the engine but not yet applied
+    let mut pending: Vec<PendingCommand> = Vec::new();
+
+    let fps = args.iter()
+        .position(|a| a == "--fps")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|fps| fps.parse().ok())
+        .unwrap_or(DEFAULT_FPS);
+    let mut frames = FrameScheduler::new(fps);
+    ui.set_skip_unchanged(!args.iter().any(|a| a == "--redraw-always"));
 
     loop {
-        ui.render(&engine)?;
-        match ui.event_receiver().recv()? {
-            Event::Input(event) => match event.code {
-                KeyCode::Char('a') => {
-                    add_gold_mine(&mut engine);
+        if quit_signal.swap(false, Ordering::Relaxed) {
+            ui.confirm_quit();
+        }
+        engine.update(Instant::now())?;
+        pending.retain(|command| match command.try_result() {
+            Some(Err(e)) => {
+                ui.report_error(&e);
+                false
+            }
+            Some(Ok(_)) => false,
+            None => true,
+        });
+        if frames.frame_due(Instant::now()) {
+            ui.render(&engine)?;
+        }
+
+        // input is handled as it arrives, but the engine isn't moved on and nothing is drawn until the next frame
+        let event = match ui.event_receiver().recv_timeout(frames.until_next_frame(Instant::now())) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(e) => break Err(e.into()),
+        };
+        let action = match event {
+            Event::Input(event) => ui.action_for(event),
+            // clicking a button does the same as pressing its hotkey
+            Event::Click { column, row } => ui.button_at(column, row),
+            Event::Resize { columns, rows } => {
+                ui.resized(columns, rows);
+                None
+            }
+        };
+        if let Some(action) = action {
+            let result = match action {
+                Action::CycleLogLevel => {
+                    let level = logging::level().next();
+                    logging::set_level(level);
+                    ui.show_message(format!("Log level: {}", level));
+                    Ok(())
+                }
+                Action::Quit => {
+                    ui.confirm_quit();
+                    Ok(())
                 }
-                KeyCode::Char('b') => {
-                    add_wood_cutter(&mut engine);
+                Action::SaveAndQuit if ui.confirming_quit() => {
+                    // stopped first so that the statistics end where the replay does
+                    engine.stop()?;
+                    match save(&engine) {
+                        Ok(()) => {
+                            log!(Level::Info, Target::Ui, "saved, exiting");
+                            break Ok(());
+                        }
+                        // the game carries on with the player still being asked, so they can try again or quit
+                        // without saving
+                        Err(e) => engine.start().and(Err(e)),
+                    }
                 }
-                KeyCode::Char('q') => {
-                    engine.stop();
-                    println!("Exiting...");
+                Action::QuitWithoutSaving if ui.confirming_quit() => {
+                    engine.stop()?;
+                    log!(Level::Info, Target::Ui, "exiting without saving");
                     break Ok(());
                 }
-                _ => (),
-            },
-            _ => (),
+                action => match ui.handle_action(action) {
+                    Some(command) => engine.send(command).map(|c| pending.push(c)),
+                    None => Ok(()),
+                },
+            };
+
+            if let Err(e) = result {
+                ui.report_error(&e);
+            }
         }
         // let mut buffer = String::new();
         // print!("> ");
@@ -65,78 +220,125 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 }
 
-fn add_gold_mine(engine: &mut Engine) {
-    if let Some(producer) = engine.get_producer("gold") {
-        let mut producer = producer.lock().unwrap();
-        let q = producer.production_quantity();
-        producer.set_production_quantity(q * 2.0);
-    } else {
-        let producer = ProducerEntity {
-            id: "gold".to_string(),
-            base_cost: 1.0,
-            cost_coefficient: 1.03,
-            product_type: ProductType {
-                name: "Gold".to_string(),
-                production_quantity: 0.01,
-                value_per_unit: 1.0,
-            },
-            production_time_ms: 1000,
-            time_elapsed: 0,
-        };
-        engine.add_producer(Box::new(producer));
+/// Saves the replay of the game so far and the statistics
+fn save(engine: &Engine) -> Result<(), continuum::Error> {
+    let registry = engine.registry()?;
+    if let Some(replay) = engine.replay()? {
+        replay.save(REPLAY_PATH, &registry)?;
     }
+    engine.snapshot()?.statistics.save(STATISTICS_PATH, &registry)
 }
 
-fn add_wood_cutter(engine: &mut Engine) {
-    match engine.get_producer("wood") {
-        Some(producer) => {
-            let mut producer = producer.lock().unwrap();
-            let q = producer.production_quantity();
-            producer.set_production_quantity(q * 2.0);
+/// Plays back a replay saved by the game, printing where it ended up and whether that matches what was recorded
+fn run_replay(config: EngineConfig, path: &str) -> Result<(), continuum::Error> {
+    let mut simulation = Simulation::new(config, &content::currencies(), &content::producers())?;
+    for (from, to, rate) in content::exchange_rates() {
+        let currency = |key| simulation.registry().currency(key).ok_or_else(|| continuum::Error::UnknownCurrency(key.to_string()));
+        let (from, to) = (currency(from)?, currency(to)?);
+        simulation.set_exchange_rate(from, to, rate)?;
+    }
+    for action in content::actions() {
+        simulation.add_action(action)?;
+    }
+    for event in content::world_events() {
+        simulation.add_world_event(event)?;
+    }
+    let replay = Replay::load(path, simulation.registry())?;
+    let snapshot = replay.play(&mut simulation);
+
+    println!("played {} commands over {} ticks", replay.commands.len(), snapshot.tick);
+    for producer in &snapshot.producers {
+        println!("    {}: {} owned", producer.key, producer.count);
+    }
+    for balance in &snapshot.wallet {
+        let recorded = replay.end_wallet.balance(balance.id);
+        // balances are summed in floating point, so they only have to agree to within rounding
+        if (balance.balance - recorded).abs() <= 1e-9 * recorded.abs().max(1.0) {
+            println!("    {}: {} (matches the recording)", balance.key, balance.balance);
+        } else {
+            println!("    {}: {} (recorded {})", balance.key, balance.balance, recorded);
         }
-        None => {
-            let producer = ProducerEntity {
-                id: "wood".to_string(),
-                base_cost: 1.0,
-                cost_coefficient: 1.04,
-                product_type: ProductType {
-                    name: "Wood".to_string(),
-                    production_quantity: 1.0,
-                    value_per_unit: 2.0,
-                },
-                production_time_ms: 500,
-                time_elapsed: 0,
-            };
-            engine.add_producer(Box::new(producer));
+    }
+    Ok(())
+}
+
+/// Adds the game's currencies and the rates they can be exchanged at
+fn setup_currencies(engine: &mut Engine) -> Result<Vec<CurrencyId>, continuum::Error> {
+    let ids = content::currencies()
+        .into_iter()
+        .map(|currency| engine.add_currency(currency))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (from, to, rate) in content::exchange_rates() {
+        let currency = |key| engine.currency_id(key)?.ok_or_else(|| continuum::Error::UnknownCurrency(key.to_string()));
+        let (from, to) = (currency(from)?, currency(to)?);
+        engine.set_exchange_rate(from, to, rate)?;
+    }
+    Ok(ids)
+}
+
+fn setup_producers(engine: &mut Engine) -> Result<Vec<ProducerId>, continuum::Error> {
+    content::producers()
+        .into_iter()
+        .map(|producer| engine.add_producer(producer))
+        .collect()
+}
+
+/// Runs each purchase strategy headlessly against the game's producers and prints how long each
+/// takes to grow the wallet to `target`
+fn run_optimizer(config: EngineConfig, target: f64) -> Result<(), continuum::Error> {
+    let optimizer = Optimizer::new(config, content::currencies(), content::producers(), target, 24 * 60 * 60 * 1000)?;
+    let registry = optimizer.registry();
+    let strategies = [Strategy::Greedy, Strategy::Payback, Strategy::Beam { width: 4 }];
+
+    for report in optimizer.compare(&strategies) {
+        match report.time_to_target_ms {
+            Some(ms) => println!("{:?}: reached {} in {:.1}s after {} purchases", report.strategy, target, ms as f64 / 1000.0, report.purchases.len()),
+            None => println!("{:?}: did not reach {}", report.strategy, target),
+        }
+
+        let order = report.purchases.iter()
+            .map(|p| format!("{}@{:.1}s", registry.producer_key(p.producer_id), p.at_ms as f64 / 1000.0))
+            .collect::<Vec<_>>();
+        println!("    order: {}", order.join(", "));
+
+        for id in registry.producer_ids().filter(|id| !report.purchases.iter().any(|r| r.producer_id == *id)) {
+            println!("    never bought: {}", registry.producer_key(id));
         }
     }
+    Ok(())
 }
 
-fn setup_producers(engine: &mut Engine) {
-    // let producer = ProducerEntity {
-    //     id: "gold".to_string(),
-    //     base_cost: 1.0,
-    //     cost_coefficient: 1.03,
-    //     product_type: ProductType {
-    //         name: "Gold".to_string(),
-    //         production_quantity: 0.01,
-    //         value_per_unit: 1.0,
-    //     },
-    //     production_time_ms: 1000,
-    //     time_elapsed: 0,
-    // };
-    // engine.add_producer(Box::new(producer));
-    // let producer = ProducerEntity {
-    //     id: "wood".to_string(),
-    //     base_cost: 1.0,
-    //     cost_coefficient: 1.04,
-    //     product_type: ProductType {
-    //         name: "Wood".to_string(),
-    //         production_quantity: 1.0,
-    //         value_per_unit: 2.0,
-    //     },
-    //     production_time_ms: 500,
-    //     time_elapsed: 0,
-    // };
-    // engine.add_producer(Box::new(producer));
+/// Times engine ticks over `count` owned producers, which share 100 products between them
+fn run_benchmark(config: EngineConfig, count: usize) -> Result<(), continuum::Error> {
+    const TICKS: u32 = 1000;
+
+    let producers = (0..count)
+        .map(|i| ProducerEntity {
+            id: format!("producer-{}", i),
+            name: format!("Producer {}", i),
+            base_cost: vec![Cost::new("coins", 1.0)],
+            cost_coefficient: 1.05,
+            product_type: ProductType {
+                id: format!("product-{}", i % 100),
+                name: format!("Product {}", i % 100),
+                production_quantity: 1.0,
+                value_per_unit: 1.0,
+                currency: "coins".to_string(),
+            },
+            production_time_ms: 100 + (i as u64 % 10) * 50,
+            time_elapsed: 0,
+            count: 1,
+        })
+        .collect::<Vec<_>>();
+    let mut sim = Simulation::new(config, &content::currencies(), &producers)?;
+
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        sim.step();
+    }
+    let elapsed = start.elapsed();
+
+    println!("{} ticks over {} producers took {:?} ({:?} per tick)", TICKS, count, elapsed, elapsed / TICKS);
+    Ok(())
 }
@@ -1,9 +1,13 @@
+use std::backtrace::Backtrace;
+use std::fs;
 use std::io::{self, stdout, Write};
+use std::panic::{self, PanicHookInfo};
 use std::thread;
-use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::sync::mpsc::{self, Receiver};
 use crossterm::{
-    event::{self, Event as CEvent, KeyEvent, EnableMouseCapture, DisableMouseCapture},
+    cursor::Show,
+    event::{self, Event as CEvent, KeyEvent, MouseButton, MouseEvent, EnableMouseCapture, DisableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -11,30 +15,69 @@ use tui::{
     backend::CrosstermBackend,
     Frame,
     Terminal,
-    widgets::{Widget, Block, Borders, Paragraph, Text, List, SelectableList, Table, Row},
-    layout::{Layout, Constraint, Direction, Rect, Alignment, Margin},
+    widgets::{Widget, Block, Borders, Paragraph, Tabs, Text},
+    layout::{Layout, Constraint, Direction, Rect, Alignment},
     style::{Style, Color},
 };
-use crate::continuum::{Engine};
-use crate::custom_widgets::{Label, Button};
+use crate::continuum::{self, Engine, EngineCommand, EngineEvent, Price, Registry, Snapshot, UpgradeTarget};
+use crate::custom_widgets::{Button, Modal};
+use crate::logging::{Level, Target};
+
+mod bindings;
+mod screen;
+mod producers;
+mod stats;
+mod help;
+mod frames;
+
+use screen::{Screen, ScreenContext};
+pub use bindings::{Action, KeyBindings};
+pub use producers::ProducersScreen;
+pub use stats::StatsScreen;
+pub use help::HelpScreen;
+pub use frames::FrameScheduler;
 
 const GAME_TITLE: &str = "[ Idle Terminal ]";
+const GAME_LOG_LINES: usize = 100;
 
-pub use crossterm::event::{KeyCode};
 
 type TerminalBackend = CrosstermBackend<io::Stdout>;
 
 pub enum Event<I> {
     Input(I),
-    Tick,
+    /// The left mouse button was pressed at this cell
+    Click { column: u16, row: u16 },
+    /// The terminal is now this size
+    Resize { columns: u16, rows: u16 },
 }
 
 pub struct UI {
     terminal: Terminal<TerminalBackend>,
     event_receiver: Receiver<Event<KeyEvent>>,
+    engine_events: Option<Receiver<EngineEvent>>,
+    game_log: Vec<String>,
+    /// Where each button was drawn in the last frame, and the action that clicking it takes
+    buttons: Vec<(Rect, Action)>,
+    bindings: KeyBindings,
+    /// The tabs that can be switched between with the number keys, in order
+    screens: Vec<Box<dyn Screen>>,
+    /// The index of the screen being shown
+    current: usize,
+    /// The snapshot the last frame was drawn from, which keys are handled against
+    snapshot: Option<Snapshot>,
+    /// The page of the help being shown over the screen, if it is open
+    help_page: Option<usize>,
+    /// Whether the player is being asked whether to save before quitting
+    quitting: bool,
+    /// Whether anything besides the engine's state has changed since the last frame, such as the tab shown
+    redraw: bool,
+    /// Whether frames are only drawn when something has changed
+    skip_unchanged: bool,
 }
 
 impl UI {
+    // crossterm's `execute!` still calls the deprecated `Error::description()` internally
+    #[allow(deprecated)]
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         enable_raw_mode()?;
 
@@ -51,26 +94,37 @@ impl UI {
         // start input handling thread
         let (tx, rx) = mpsc::channel();
 
+        // frames are drawn on their own schedule (see `FrameScheduler`), so this only passes input on
         thread::spawn(move || {
             loop {
-                // poll for tick rate duration, if no events, sent tick event.
-                if event::poll(Duration::from_millis(100)).unwrap() {
-                    match event::read().unwrap() {
-                        CEvent::Key(key) => tx.send(Event::Input(key)).unwrap(),
-                        _ => (),
-                    }
-                    // if let CEvent::Key(key) = event::read().unwrap() {
-                    //     tx.send(Event::Input(key)).unwrap();
-                    // }
-                }
+                let sent = match event::read().unwrap() {
+                    CEvent::Key(key) => tx.send(Event::Input(key)),
+                    CEvent::Mouse(MouseEvent::Down(MouseButton::Left, column, row, _)) => tx.send(Event::Click { column, row }),
+                    CEvent::Resize(columns, rows) => tx.send(Event::Resize { columns, rows }),
+                    _ => Ok(()),
+                };
 
-                tx.send(Event::Tick).unwrap();
+                // the UI has been dropped, so there's no one left to send events to
+                if sent.is_err() {
+                    break;
+                }
             }
         });
 
         Ok(UI{
             terminal,
             event_receiver: rx,
+            engine_events: None,
+            game_log: Vec::new(),
+            buttons: Vec::new(),
+            bindings: KeyBindings::default(),
+            screens: Vec::new(),
+            current: 0,
+            snapshot: None,
+            help_page: None,
+            quitting: false,
+            redraw: true,
+            skip_unchanged: true,
         })
     }
 
@@ -78,117 +132,435 @@ impl UI {
         &self.event_receiver
     }
 
-    pub fn render(&mut self, engine: &Engine) -> Result<(), io::Error> {
-        self.terminal.draw(|mut f| {
-            let size = f.size();
-            let chunks = Layout::default()
-                .constraints([Constraint::Length(5), Constraint::Min(0)].as_ref())
-                .split(size);
-            
-            Self::render_top_bar(&mut f, engine, chunks[0]);
-            Self::render_body(&mut f, engine, chunks[1]);
-        })
+    /// The action of the button drawn at `column`, `row` in the last frame, if any
+    pub fn button_at(&self, column: u16, row: u16) -> Option<Action> {
+        // the buttons are covered by the help
+        if self.help_page.is_some() {
+            return None;
+        }
+        self.buttons.iter()
+            .find(|(area, _)| column >= area.left() && column < area.right() && row >= area.top() && row < area.bottom())
+            .map(|(_, key)| *key)
     }
 
-    fn render_top_bar(f: &mut Frame<TerminalBackend>, engine: &Engine, area: Rect) {
-        Paragraph::new([Text::raw("\nWelcome to the Terminal Idle Game - where not even the develop knows whats going to happen...")].iter())
-            .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    .title(GAME_TITLE)
-                    .borders(Borders::ALL)
-            )
-            .render(f, area);
+    /// Ask the player whether to save before quitting, over whatever else is shown.
+    /// Their answer comes back as `Action::SaveAndQuit`, `Action::QuitWithoutSaving` or `Action::Close` to cancel
+    pub fn confirm_quit(&mut self) {
+        self.quitting = true;
+        self.redraw = true;
+        self.help_page = None;
+        // the buttons beneath the dialog can't be clicked
+        self.buttons.clear();
     }
 
-    fn render_body(f: &mut Frame<TerminalBackend>, engine: &Engine, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(50), Constraint::Min(0)].as_ref())
-            .split(area);
+    /// Whether the player is being asked whether to save before quitting
+    pub fn confirming_quit(&self) -> bool {
+        self.quitting
+    }
 
-        Self::render_products(f, engine, chunks[0]);
-        Self::render_actions(f, engine, chunks[1]);
+    /// Whether to skip drawing frames when nothing has changed since the last one, which is the default.
+    /// Drawing every frame regardless can help if the terminal gets out of step with what was drawn
+    pub fn set_skip_unchanged(&mut self, skip_unchanged: bool) {
+        self.skip_unchanged = skip_unchanged;
     }
 
-    fn render_products(f: &mut Frame<TerminalBackend>, engine: &Engine, area: Rect) {
-        let products = engine.get_products();
-        let text_vec = products.iter().map(|(k, v)| {
-            (k.clone(), format!("{}", v))
-        }).collect::<Vec<_>>();
+    /// Called when the terminal has been resized, so that the next frame is drawn to fit it
+    pub fn resized(&mut self, columns: u16, rows: u16) {
+        log!(Level::Debug, Target::Ui, "terminal resized to {}x{}", columns, rows);
+        self.redraw = true;
+    }
 
-        if text_vec.len() > 0 {
-            Table::new(
-                ["Item", "Qty"].into_iter(),
-                text_vec.iter().map(|(label, value)| {
-                    Row::Data(vec![label, value].into_iter())
-                })
-            )
-            .block(Block::default().title("[ Products ]").borders(Borders::ALL))
-            .header_style(Style::default().fg(Color::Cyan))
-            .widths(&[Constraint::Length(9), Constraint::Min(0)])
-            .style(Style::default().fg(Color::White))
-            .column_spacing(1)
-            .render(f, area);
-        } else {
-            Block::default()
-                .borders(Borders::ALL)
-                .title("[ Products ]")
-                .render(f, area);
+    /// Add a tab, which is switched to by pressing its number
+    pub fn add_screen(&mut self, screen: Box<dyn Screen>) {
+        self.screens.push(screen);
+    }
+
+    /// Use `bindings` in place of the default keys
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+        self.redraw = true;
+    }
+
+    /// The action a key press is bound to, if any
+    pub fn action_for(&self, event: KeyEvent) -> Option<Action> {
+        self.bindings.action(event.into())
+    }
+
+    /// Handles an action taken while playing, returning the command it asks the engine for, if any.
+    /// Switching tabs, accepting offers and prestiging work from every tab, and anything else is handled by the screen being shown.
+    /// While the help is open, only the actions that page through and close it do anything
+    pub fn handle_action(&mut self, action: Action) -> Option<EngineCommand> {
+        // most actions change what's shown in some way, even if the engine's state doesn't
+        self.redraw = true;
+
+        if self.quitting {
+            if action == Action::Close {
+                self.quitting = false;
+                self.buttons.clear();
+            }
+            return None;
+        }
+        if let Some(page) = self.help_page {
+            match action {
+                Action::ToggleHelp | Action::Close => self.help_page = None,
+                // clamped to the last page when the help is next drawn
+                Action::NextPage => self.help_page = Some(page + 1),
+                Action::PreviousPage => self.help_page = Some(page.saturating_sub(1)),
+                _ => (),
+            }
+            return None;
+        }
+
+        match action {
+            Action::ToggleHelp => {
+                self.help_page = Some(0);
+                None
+            }
+            Action::ShowScreen(index) => {
+                if index < self.screens.len() {
+                    self.current = index;
+                    // the old screen's buttons are gone until the next frame is drawn
+                    self.buttons.clear();
+                }
+                None
+            }
+            // the offer is shown in the top bar, whichever screen is open
+            Action::AcceptOffer => Some(EngineCommand::AcceptOffer),
+            Action::Prestige => Some(EngineCommand::Prestige),
+            action => {
+                let snapshot = self.snapshot.as_ref()?;
+                self.screens.get_mut(self.current)?.handle_action(action, snapshot)
+            }
         }
     }
 
-    fn render_actions(f: &mut Frame<TerminalBackend>, engine: &Engine, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(5), Constraint::Min(0)].as_ref())
-            .split(area);
+    /// Show the events sent to `receiver` (see `Engine::subscribe()`) in the game log
+    pub fn watch(&mut self, receiver: Receiver<EngineEvent>) {
+        self.engine_events = Some(receiver);
+    }
+
+    /// Show an error from the engine in the game log
+    pub fn report_error(&mut self, error: &continuum::Error) {
+        log!(Level::Error, Target::Ui, "{}", error);
+        self.show_message(format!("Error: {}", error));
+    }
+
+    /// Show a message in the game log
+    pub fn show_message(&mut self, message: String) {
+        self.game_log.push(message);
+        self.redraw = true;
+
+        let overflow = self.game_log.len().saturating_sub(GAME_LOG_LINES);
+        self.game_log.drain(..overflow);
+    }
 
-        Block::default()
-            .borders(Borders::ALL)
-            .title("[ Actions ]")
-            .render(f, chunks[0]);
+    /// Draws a frame showing the engine's current state, unless nothing has changed since the last frame and
+    /// unchanged frames are being skipped
+    pub fn render(&mut self, engine: &Engine) -> Result<(), Box<dyn std::error::Error>> {
+        // gather everything from the engine up front, as errors can't be returned from inside `draw()`.
+        // Taking a single snapshot means every part of the frame shows the same moment
+        let snapshot = engine.snapshot()?;
 
-        // TODO: render some buttons here
-        let inner_rect = Rect {
-            x: chunks[0].x + 1,
-            y: chunks[0].y + 1,
-            width: chunks[0].width-2,
-            height: chunks[0].height-2,
+        let events = match &self.engine_events {
+            Some(receiver) => receiver.try_iter().collect::<Vec<_>>(),
+            None => Vec::new(),
         };
+        // the registry still knows the names of producers that have since been removed
+        let registry = engine.registry()?;
+        for event in events {
+            if let Some(message) = Self::describe_event(&registry, &event) {
+                self.show_message(message);
+            }
+        }
+
+        if self.skip_unchanged && !self.redraw && self.snapshot.as_ref().is_some_and(|last| frames::unchanged(last, &snapshot)) {
+            return Ok(());
+        }
+        self.redraw = false;
 
-        let btn_chunks = Layout::default()
+        let wallet = snapshot.wallet.iter()
+            .map(|b| format!("{:.2} {}", b.balance, b.name))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let buffs = snapshot.buffs.iter()
+            .map(|b| format!("{} ({}s)", b.buff.name, b.remaining_ms.div_ceil(1000)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let offer = match &snapshot.offer {
+            Some(active) => {
+                let describe = |costs: &[continuum::Cost]| costs.iter()
+                    .map(|c| format!("{:.2} {}", c.amount, snapshot.wallet.iter().find(|b| b.key == c.currency).map_or(c.currency.as_str(), |b| b.name.as_str())))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let accept = match self.bindings.keys(Action::AcceptOffer).first() {
+                    Some(key) => format!(" - press {} to accept", key),
+                    None => String::new(),
+                };
+                format!("{} offers {} for {} ({}s){}",
+                    active.name, describe(&active.offer.receive), describe(&active.offer.give), active.remaining_ms.div_ceil(1000), accept)
+            }
+            None => String::new(),
+        };
+
+        let titles = self.screens.iter()
+            .map(|screen| screen.title().to_string())
+            .collect::<Vec<_>>();
+        // each tab is labelled with the key that switches to it
+        let tabs = titles.iter()
+            .enumerate()
+            .map(|(i, title)| match self.bindings.keys(Action::ShowScreen(i)).first() {
+                Some(key) => format!("{} {}", key, title),
+                None => title.clone(),
+            })
+            .collect::<Vec<_>>();
+        let current = self.current;
+        let context = ScreenContext {
+            snapshot: &snapshot,
+            registry: &registry,
+            game_log: &self.game_log,
+            bindings: &self.bindings,
+            screen_titles: &titles,
+        };
+        let help = match self.help_page {
+            Some(_) => HelpScreen::help_text(&context),
+            None => Vec::new(),
+        };
+        let key = |action| self.bindings.keys(action).first().map_or("unbound".to_string(), |k| k.to_string());
+        let help_footer = format!("{}/{} to turn the page, {} to close", key(Action::PreviousPage), key(Action::NextPage), key(Action::ToggleHelp));
+        let quit_choices = [
+            format!("[{}] Save and quit", key(Action::SaveAndQuit)),
+            format!("[{}] Quit without saving", key(Action::QuitWithoutSaving)),
+            format!("[{}] Cancel", key(Action::Close)),
+        ];
+        let quitting = self.quitting;
+        let screen = self.screens.get_mut(current);
+        let buttons = &mut self.buttons;
+        let help_page = &mut self.help_page;
+        self.terminal.draw(|mut f| {
+            let size = f.size();
+            let chunks = Layout::default()
+                .constraints([Constraint::Length(8), Constraint::Min(0)].as_ref())
+                .split(size);
+
+            Self::render_top_bar(&mut f, &wallet, &buffs, &offer, &tabs, current, chunks[0]);
+            *buttons = match screen {
+                Some(screen) => screen.render(&mut f, &context, chunks[1]),
+                None => Vec::new(),
+            };
+
+            if let Some(page) = help_page {
+                let area = Self::centered(size, 80, 80);
+                let mut modal = Modal::default();
+                modal.title("[ Help ]").lines(&help).footer(&help_footer).style(Style::default().fg(Color::White));
+                *page = (*page).min(modal.page_count(area) - 1);
+                modal.page(*page).render(&mut f, area);
+            }
+            if quitting {
+                *buttons = Self::render_quit_dialog(&mut f, &quit_choices, size);
+            }
+        })?;
+
+        self.snapshot = Some(snapshot);
+        Ok(())
+    }
+
+    /// What to write in the game log about `event`, if anything
+    fn describe_event(registry: &Registry, event: &EngineEvent) -> Option<String> {
+        let message = match event {
+            EngineEvent::Purchased { id, count, cost } => 
+                format!("Bought {} #{} for {}", registry.producer_name(*id), count, Self::describe_price(registry, cost)),
+            EngineEvent::Sold { id, count, refund } => 
+                format!("Sold {} {} for {}", count, registry.producer_name(*id), Self::describe_price(registry, refund)),
+            EngineEvent::Removed { id, refund } => 
+                format!("Removed {}, refunded {}", registry.producer_name(*id), Self::describe_price(registry, refund)),
+            EngineEvent::Upgraded { target, name, multiplier } => {
+                let target = match target {
+                    UpgradeTarget::Producer(id) => registry.producer_name(*id),
+                    UpgradeTarget::Action(id) => registry.action_name(*id),
+                };
+                format!("{} upgraded with {} (x{})", target, name, multiplier)
+            }
+            // the action's button already shows what the next use will make
+            EngineEvent::ActionPerformed { .. } => return None,
+            EngineEvent::Prestiged { level } =>
+                format!("Prestiged to level {}", level),
+            EngineEvent::BuffStarted { name, duration_ms } =>
+                format!("{} active for {}s", name, duration_ms / 1000),
+            EngineEvent::BuffEnded { name } =>
+                format!("{} has worn off", name),
+            EngineEvent::Exchanged { from, to, amount, received } =>
+                format!("Exchanged {:.2} {} for {:.2} {}", amount, registry.currency_name(*from), received, registry.currency_name(*to)),
+            EngineEvent::WorldEvent { name, description } =>
+                format!("{}! {}", name, description),
+            EngineEvent::ProductDestroyed { product, amount } =>
+                format!("Lost {:.2} {}", amount, registry.product_name(*product)),
+            EngineEvent::OfferAccepted { name, paid, received } =>
+                format!("Traded {} with the {} for {}", Self::describe_price(registry, paid), name, Self::describe_price(registry, received)),
+            EngineEvent::OfferExpired { name } =>
+                format!("The {} has moved on", name),
+        };
+        Some(message)
+    }
+
+    fn describe_price(registry: &Registry, price: &Price) -> String {
+        price.iter()
+            .map(|(currency, amount)| format!("{:.2} {}", amount, registry.currency_name(currency)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Asks whether to save before quitting, with a button for each of `choices`: save and quit, quit without saving,
+    /// and cancel. Returns where the buttons were drawn, with their actions
+    fn render_quit_dialog(f: &mut Frame<TerminalBackend>, choices: &[String; 3], area: Rect) -> Vec<(Rect, Action)> {
+        let width = area.width.min(96);
+        let height = area.height.min(9);
+        let area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let lines = [String::new(), "Save the replay and statistics before quitting?".to_string()];
+        Modal::default()
+            .title("[ Quit ]")
+            .lines(&lines)
+            .style(Style::default().fg(Color::White))
+            .render(f, area);
+
+        let button_row = Rect {
+            x: area.x + 1,
+            y: area.y + 4,
+            width: area.width.saturating_sub(2),
+            height: 3,
+        }.intersection(area);
+        let columns = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(1,3), Constraint::Ratio(1, 3)].as_ref())
-            .split(inner_rect);
+            .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(1, 3), Constraint::Ratio(1, 3)].as_ref())
+            .split(button_row);
+
+        let styles = [
+            Style::default().fg(Color::Black).bg(Color::Green),
+            Style::default().fg(Color::Black).bg(Color::Red),
+            Style::default().fg(Color::Black).bg(Color::White),
+        ];
+        let actions = [Action::SaveAndQuit, Action::QuitWithoutSaving, Action::Close];
+        for ((choice, style), column) in choices.iter().zip(styles.iter()).zip(columns.iter()) {
+            Button::default()
+                .text(choice)
+                .style(*style)
+                .render(f, *column);
+        }
+        columns.into_iter().zip(actions.iter().copied()).collect()
+    }
 
-        // println!("btn_chunks = {:?}", btn_chunks);
+    /// A rect in the middle of `area`, taking up the given percentages of its width and height
+    fn centered(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
+        let width = (area.width as u32 * width_percent as u32 / 100) as u16;
+        let height = (area.height as u32 * height_percent as u32 / 100) as u16;
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
+    }
 
-        Button::default()
-            .text("[A] Gold Miner")
-            .style(Style::default().fg(Color::White).bg(Color::Cyan))
-            .render(f, btn_chunks[0]);
+    fn render_top_bar(f: &mut Frame<TerminalBackend>, wallet: &str, buffs: &str, offer: &str, tabs: &[String], current: usize, area: Rect) {
+        let text = [
+            Text::raw("\nWelcome to the Terminal Idle Game - where not even the develop knows whats going to happen...\n"),
+            Text::styled(format!("Funds: {}\n", wallet), Style::default().fg(Color::Yellow)),
+            Text::styled(format!("{}\n", buffs), Style::default().fg(Color::Green)),
+            Text::styled(offer, Style::default().fg(Color::Magenta)),
+        ];
 
-        Button::default()
-            .text("[B] Wood Cutter")
-            .style(Style::default().fg(Color::Black).bg(Color::Yellow))
-            .render(f, btn_chunks[1]);
+        Paragraph::new(text.iter())
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .title(GAME_TITLE)
+                    .borders(Borders::ALL)
+            )
+            .render(f, area);
 
-        Block::default()
-            .borders(Borders::ALL)
-            .title("[ Game Log ]")
-            .render(f, chunks[1]);
+        // the tabs sit along the bottom of the bar
+        let tabs_area = Rect {
+            x: area.x + 1,
+            y: area.y + area.height.saturating_sub(2),
+            width: area.width.saturating_sub(2),
+            height: 1,
+        };
+        Tabs::default()
+            .titles(tabs)
+            .select(current)
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+            .render(f, tabs_area);
     }
 }
 
 
 impl Drop for UI {
     fn drop(&mut self) {
-        println!("UI dropping");
-        disable_raw_mode().unwrap();
-        execute!(self.terminal.backend_mut(), LeaveAlternateScreen).unwrap();
-        execute!(self.terminal.backend_mut(), DisableMouseCapture).unwrap();
-        self.terminal.show_cursor().unwrap();
+        log!(Level::Debug, Target::Ui, "UI dropping");
+        if let Err(e) = restore_terminal() {
+            log!(Level::Error, Target::Ui, "couldn't restore the terminal: {}", e);
+        }
     }
 }
 
+/// Puts the terminal back the way `UI::new()` found it: out of raw mode and the alternate screen, with the mouse
+/// released and the cursor shown
+// crossterm's `execute!` still calls the deprecated `Error::description()` internally
+#[allow(deprecated)]
+fn restore_terminal() -> crossterm::Result<()> {
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture, Show)?;
+    Ok(())
+}
+
+/// Makes a panic on the calling thread, which should be the one that draws the game, restore the terminal before the
+/// panic message is printed, so that it can be read, then write a crash report to `report_path`. The panic then
+/// unwinds as usual and ends the game.
+/// Panics on other threads are only logged, as the game is still being drawn: they unwind to wherever the thread is
+/// joined, e.g. the engine's threads are reported as `continuum::Error::ThreadPanicked` when it is stopped
+pub fn install_panic_hook(report_path: &'static str) {
+    let ui_thread = thread::current().id();
+    let print_panic = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        if thread.id() != ui_thread {
+            log!(Level::Error, Target::Ui, "thread {} {}", thread.name().unwrap_or("<unnamed>"), info);
+            return;
+        }
+
+        // errors are ignored as there's nothing more that can be done about them
+        let _ = restore_terminal();
+        print_panic(info);
+
+        match write_crash_report(report_path, info) {
+            Ok(()) => eprintln!("A crash report has been written to {}", report_path),
+            Err(e) => eprintln!("Couldn't write a crash report to {}: {}", report_path, e),
+        }
+    }));
+}
+
+/// Writes what panicked, where, and the backtrace to `path`
+fn write_crash_report(path: &str, info: &PanicHookInfo) -> io::Result<()> {
+    let payload = info.payload();
+    let message = payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("(no message)");
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+    let report = format!(
+        "Idle Terminal {} crash report\ntime: {}s since the epoch\nthread: {}\nmessage: {}\nlocation: {}\n\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        time,
+        thread::current().name().unwrap_or("<unnamed>"),
+        message,
+        info.location().map_or("unknown".to_string(), |l| l.to_string()),
+        Backtrace::force_capture(),
+    );
+    fs::write(path, report)
+}
+
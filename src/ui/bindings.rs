@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::continuum::{ActionId, ProducerId};
+
+const HEADER: &str = "bindings 1";
+
+/// The keys producers are bound to by default, in order: every letter that isn't bound to something else.
+/// Shift and the same letter sells
+const PRODUCER_KEYS: &str = "abcdehijknoprstuvwyz";
+/// The keys manual actions are bound to by default, in order
+const ACTION_KEYS: &str = "g";
+
+/// Something the player can do from the keyboard, or by clicking a button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    /// Ask whether to save before quitting
+    Quit,
+    /// The choices when asked about quitting
+    SaveAndQuit,
+    QuitWithoutSaving,
+    /// Open or close the help
+    ToggleHelp,
+    CycleLogLevel,
+    /// Switch to the tab at this index
+    ShowScreen(usize),
+    AcceptOffer,
+    /// Buy one of the producer. Bound by ID rather than by position, so that removing a producer doesn't
+    /// move the keys of the ones after it
+    Buy(ProducerId),
+    /// Sell one of the producer
+    Sell(ProducerId),
+    /// Use the manual action
+    Perform(ActionId),
+    BuyFrenzy,
+    Exchange,
+    /// Start over with a permanent bonus. Bound to a chord so it isn't pressed by accident
+    Prestige,
+    ScrollUp,
+    ScrollDown,
+    /// Turn the page of the help
+    NextPage,
+    PreviousPage,
+    /// Close the help, or cancel quitting
+    Close,
+}
+
+impl Action {
+    /// Reads the name an action is written as in the bindings file, e.g. `buy-1`.
+    /// Producers and manual actions are numbered in the order they were added to the engine, from 1
+    pub fn parse(name: &str) -> Option<Action> {
+        let numbered = |prefix: &str| name.strip_prefix(prefix)
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .map(|n| n - 1);
+
+        let action = match name {
+            "quit" => Action::Quit,
+            "save-and-quit" => Action::SaveAndQuit,
+            "quit-without-saving" => Action::QuitWithoutSaving,
+            "help" => Action::ToggleHelp,
+            "log-level" => Action::CycleLogLevel,
+            "accept-offer" => Action::AcceptOffer,
+            "frenzy" => Action::BuyFrenzy,
+            "exchange" => Action::Exchange,
+            "prestige" => Action::Prestige,
+            "scroll-up" => Action::ScrollUp,
+            "scroll-down" => Action::ScrollDown,
+            "next-page" => Action::NextPage,
+            "previous-page" => Action::PreviousPage,
+            "close" => Action::Close,
+            _ => {
+                if let Some(n) = numbered("screen-") {
+                    Action::ShowScreen(n)
+                } else if let Some(n) = numbered("buy-") {
+                    Action::Buy(ProducerId::from_index(n))
+                } else if let Some(n) = numbered("sell-") {
+                    Action::Sell(ProducerId::from_index(n))
+                } else if let Some(n) = numbered("perform-") {
+                    Action::Perform(ActionId::from_index(n))
+                } else {
+                    return None;
+                }
+            }
+        };
+        Some(action)
+    }
+}
+
+/// Written as read by `Action::parse()`. Numbered actions count from 1
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Quit => write!(f, "quit"),
+            Action::SaveAndQuit => write!(f, "save-and-quit"),
+            Action::QuitWithoutSaving => write!(f, "quit-without-saving"),
+            Action::ToggleHelp => write!(f, "help"),
+            Action::CycleLogLevel => write!(f, "log-level"),
+            Action::ShowScreen(n) => write!(f, "screen-{}", n + 1),
+            Action::AcceptOffer => write!(f, "accept-offer"),
+            Action::Buy(id) => write!(f, "buy-{}", id.index() + 1),
+            Action::Sell(id) => write!(f, "sell-{}", id.index() + 1),
+            Action::Perform(id) => write!(f, "perform-{}", id.index() + 1),
+            Action::BuyFrenzy => write!(f, "frenzy"),
+            Action::Exchange => write!(f, "exchange"),
+            Action::Prestige => write!(f, "prestige"),
+            Action::ScrollUp => write!(f, "scroll-up"),
+            Action::ScrollDown => write!(f, "scroll-down"),
+            Action::NextPage => write!(f, "next-page"),
+            Action::PreviousPage => write!(f, "previous-page"),
+            Action::Close => write!(f, "close"),
+        }
+    }
+}
+
+/// A key together with the modifiers held while pressing it.
+///
+/// Letters are always stored in lower case, with shift as a modifier, so that `A` and `shift+a` are the same key.
+/// Shift is dropped from other characters, as the character itself already says whether shift was held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => Key { code: KeyCode::Char(c.to_ascii_lowercase()), modifiers: modifiers | KeyModifiers::SHIFT },
+            KeyCode::Char(c) if !c.is_ascii_alphabetic() => Key { code, modifiers: modifiers - KeyModifiers::SHIFT },
+            _ => Key { code, modifiers },
+        }
+    }
+
+    pub fn char(c: char) -> Self {
+        Key::new(KeyCode::Char(c), KeyModifiers::empty())
+    }
+
+    /// Reads a key written as by `Display`, e.g. `a`, `shift+a`, `ctrl+alt+up` or `f1`
+    pub fn parse(text: &str) -> Option<Key> {
+        let (modifier_names, name) = match text.rsplit_once('+') {
+            Some((modifiers, name)) if !name.is_empty() => (modifiers, name),
+            // `+` on its own, or after modifiers as in `ctrl++`
+            _ if text.ends_with('+') => (text[..text.len() - 1].trim_end_matches('+'), "+"),
+            _ => ("", text),
+        };
+
+        let mut modifiers = KeyModifiers::empty();
+        for modifier in modifier_names.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "shift" => KeyModifiers::SHIFT,
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                _ => return None,
+            };
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                other => match other.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return None,
+                },
+            },
+        };
+        Some(Key::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "ctrl"), (KeyModifiers::ALT, "alt"), (KeyModifiers::SHIFT, "shift")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "backtab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Why a bindings file couldn't be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingError {
+    Io(String),
+    Invalid { line: usize, reason: String },
+    /// An action was listed more than once
+    Duplicate { line: usize, action: Action },
+    /// A key was bound to two different actions
+    Conflict { key: Key, first: Action, second: Action },
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingError::Io(e) => write!(f, "couldn't read key bindings: {}", e),
+            BindingError::Invalid { line, reason } => write!(f, "invalid key bindings on line {}: {}", line, reason),
+            BindingError::Duplicate { line, action } => write!(f, "{} is bound again on line {}", action, line),
+            BindingError::Conflict { key, first, second } => write!(f, "{} is bound to both {} and {}", key, first, second),
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+/// Which keys do what.
+///
+/// The defaults can be changed with a bindings file: a `bindings 1` header, then one line per action of its name
+/// followed by the keys to bind it to, separated by spaces, e.g. `buy-1 a ctrl+b`. Listing an action replaces its
+/// default keys, and listing it without any keys unbinds it. Blank lines and lines starting with `#` are skipped
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    /// The keys bound to each action, in the order they were listed
+    keys: BTreeMap<Action, Vec<Key>>,
+    /// The action each key is bound to
+    actions: HashMap<Key, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut keys = BTreeMap::new();
+        // raw mode turns ctrl+c into an ordinary key press rather than a signal
+        keys.insert(Action::Quit, vec![Key::char('q'), Key::new(KeyCode::Char('c'), KeyModifiers::CONTROL)]);
+        keys.insert(Action::SaveAndQuit, vec![Key::new(KeyCode::Enter, KeyModifiers::empty())]);
+        keys.insert(Action::QuitWithoutSaving, vec![Key::char('Q')]);
+        keys.insert(Action::ToggleHelp, vec![Key::char('?')]);
+        keys.insert(Action::CycleLogLevel, vec![Key::char('l')]);
+        for (i, c) in ('1'..='9').enumerate() {
+            keys.insert(Action::ShowScreen(i), vec![Key::char(c)]);
+        }
+        keys.insert(Action::AcceptOffer, vec![Key::char('m')]);
+        for (i, c) in PRODUCER_KEYS.chars().enumerate() {
+            keys.insert(Action::Buy(ProducerId::from_index(i)), vec![Key::char(c)]);
+            keys.insert(Action::Sell(ProducerId::from_index(i)), vec![Key::char(c.to_ascii_uppercase())]);
+        }
+        for (i, c) in ACTION_KEYS.chars().enumerate() {
+            keys.insert(Action::Perform(ActionId::from_index(i)), vec![Key::char(c)]);
+        }
+        keys.insert(Action::BuyFrenzy, vec![Key::char('f')]);
+        keys.insert(Action::Exchange, vec![Key::char('x')]);
+        keys.insert(Action::Prestige, vec![Key::new(KeyCode::Char('p'), KeyModifiers::CONTROL)]);
+        keys.insert(Action::ScrollUp, vec![Key::new(KeyCode::Up, KeyModifiers::empty())]);
+        keys.insert(Action::ScrollDown, vec![Key::new(KeyCode::Down, KeyModifiers::empty())]);
+        keys.insert(Action::NextPage, vec![Key::new(KeyCode::Right, KeyModifiers::empty()), Key::new(KeyCode::PageDown, KeyModifiers::empty())]);
+        keys.insert(Action::PreviousPage, vec![Key::new(KeyCode::Left, KeyModifiers::empty()), Key::new(KeyCode::PageUp, KeyModifiers::empty())]);
+        keys.insert(Action::Close, vec![Key::new(KeyCode::Esc, KeyModifiers::empty())]);
+
+        Self::from_keys(keys).expect("the default key bindings don't conflict")
+    }
+}
+
+impl KeyBindings {
+    /// Indexes `keys` by key, checking that no key is bound to two actions
+    fn from_keys(keys: BTreeMap<Action, Vec<Key>>) -> Result<Self, BindingError> {
+        let mut actions = HashMap::new();
+        for (action, action_keys) in &keys {
+            for key in action_keys {
+                match actions.insert(*key, *action) {
+                    Some(first) if first != *action => return Err(BindingError::Conflict { key: *key, first, second: *action }),
+                    _ => (),
+                }
+            }
+        }
+        Ok(KeyBindings { keys, actions })
+    }
+
+    /// The action `key` is bound to, if any
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.actions.get(&key).copied()
+    }
+
+    /// The keys `action` is bound to, which may be none
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// Every bound action with its keys, in the order of `Action`
+    pub fn iter(&self) -> impl Iterator<Item = (Action, &[Key])> + '_ {
+        self.keys.iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .map(|(action, keys)| (*action, keys.as_slice()))
+    }
+
+    /// Reads the bindings file at `path` over the defaults
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BindingError> {
+        let text = fs::read_to_string(path).map_err(|e| BindingError::Io(e.to_string()))?;
+        Self::parse(&text)
+    }
+
+    /// Reads a bindings file over the defaults. See `KeyBindings` for the format
+    pub fn parse(text: &str) -> Result<Self, BindingError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(BindingError::Invalid { line: 1, reason: format!("expected '{}'", HEADER) }),
+        }
+
+        let mut keys = KeyBindings::default().keys;
+        let mut listed = HashMap::new();
+        for (number, line) in lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('#')) {
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default();
+            let action = Action::parse(name)
+                .ok_or_else(|| BindingError::Invalid { line: number, reason: format!("unknown action '{}'", name) })?;
+            if listed.insert(action, number).is_some() {
+                return Err(BindingError::Duplicate { line: number, action });
+            }
+
+            let action_keys = fields
+                .map(|text| Key::parse(text).ok_or_else(|| BindingError::Invalid { line: number, reason: format!("unknown key '{}'", text) }))
+                .collect::<Result<Vec<_>, _>>()?;
+            keys.insert(action, action_keys);
+        }
+        Self::from_keys(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::new(code, modifiers)
+    }
+
+    #[test]
+    fn keys_are_read_as_written() {
+        assert_eq!(Key::parse("a"), Some(Key::char('a')));
+        assert_eq!(Key::parse("A"), Some(key(KeyCode::Char('a'), KeyModifiers::SHIFT)));
+        assert_eq!(Key::parse("shift+a"), Key::parse("A"));
+        assert_eq!(Key::parse("ctrl+alt+up"), Some(key(KeyCode::Up, KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        assert_eq!(Key::parse("f12"), Some(key(KeyCode::F(12), KeyModifiers::empty())));
+        assert_eq!(Key::parse("space"), Some(Key::char(' ')));
+        // `+` is both a key and the separator
+        assert_eq!(Key::parse("+"), Some(Key::char('+')));
+        assert_eq!(Key::parse("ctrl++"), Some(key(KeyCode::Char('+'), KeyModifiers::CONTROL)));
+        // the character already says whether shift was held
+        assert_eq!(Key::parse("shift+?"), Some(Key::char('?')));
+
+        assert_eq!(Key::parse("hyper+a"), None);
+        assert_eq!(Key::parse("ctrl+nothing"), None);
+        assert_eq!(Key::parse(""), None);
+    }
+
+    #[test]
+    fn keys_and_actions_are_written_as_they_are_read() {
+        let bindings = KeyBindings::default();
+
+        for (action, keys) in bindings.iter() {
+            assert_eq!(Action::parse(&action.to_string()), Some(action));
+            for key in keys {
+                assert_eq!(Key::parse(&key.to_string()), Some(*key));
+            }
+        }
+    }
+
+    #[test]
+    fn listed_actions_replace_their_default_keys() {
+        let bindings = KeyBindings::parse("bindings 1\n# comment\n\nbuy-1 ctrl+a f1\nsell-1\n").unwrap();
+        let first = ProducerId::from_index(0);
+
+        assert_eq!(bindings.action(key(KeyCode::Char('a'), KeyModifiers::CONTROL)), Some(Action::Buy(first)));
+        assert_eq!(bindings.action(key(KeyCode::F(1), KeyModifiers::empty())), Some(Action::Buy(first)));
+        assert_eq!(bindings.action(Key::char('a')), None);
+        assert!(bindings.keys(Action::Sell(first)).is_empty());
+        // everything else keeps its default keys
+        assert_eq!(bindings.action(Key::char('b')), Some(Action::Buy(ProducerId::from_index(1))));
+    }
+
+    #[test]
+    fn conflicting_keys_are_rejected() {
+        assert_eq!(
+            KeyBindings::parse("bindings 1\nbuy-1 q").err(),
+            Some(BindingError::Conflict { key: Key::char('q'), first: Action::Quit, second: Action::Buy(ProducerId::from_index(0)) }),
+        );
+
+        let mut keys = BTreeMap::new();
+        keys.insert(Action::ScrollUp, vec![Key::char('k')]);
+        keys.insert(Action::ScrollDown, vec![Key::char('j'), Key::char('k')]);
+        assert!(matches!(KeyBindings::from_keys(keys), Err(BindingError::Conflict { .. })));
+
+        // a key listed twice for the same action isn't a conflict
+        let mut keys = BTreeMap::new();
+        keys.insert(Action::ScrollUp, vec![Key::char('k'), Key::char('k')]);
+        assert!(KeyBindings::from_keys(keys).is_ok());
+    }
+
+    #[test]
+    fn actions_listed_twice_are_rejected() {
+        assert_eq!(
+            KeyBindings::parse("bindings 1\nbuy-1 ctrl+a\n\nbuy-1 ctrl+b").err(),
+            Some(BindingError::Duplicate { line: 4, action: Action::Buy(ProducerId::from_index(0)) }),
+        );
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        assert!(matches!(KeyBindings::parse("buy-1 a"), Err(BindingError::Invalid { line: 1, .. })));
+        assert!(matches!(KeyBindings::parse("bindings 1\nbuy-0 a"), Err(BindingError::Invalid { line: 2, .. })));
+        assert!(matches!(KeyBindings::parse("bindings 1\nbuy-1 hyper+a"), Err(BindingError::Invalid { line: 2, .. })));
+    }
+}
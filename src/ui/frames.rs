@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use crate::continuum::statistics::RunStatistics;
+use crate::continuum::Snapshot;
+
+/// Decides when to draw, so that the game is drawn at a steady rate however often input arrives
+#[derive(Debug)]
+pub struct FrameScheduler {
+    frame_time: Duration,
+    next_frame: Instant,
+}
+
+impl FrameScheduler {
+    /// Schedules `target_fps` frames a second, the first of them straight away
+    pub fn new(target_fps: u32) -> Self {
+        FrameScheduler {
+            frame_time: Duration::from_secs(1) / target_fps.max(1),
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// Whether a frame is due at `now`. If it is, the next one is scheduled a frame's time later
+    pub fn frame_due(&mut self, now: Instant) -> bool {
+        if now < self.next_frame {
+            return false;
+        }
+
+        // frames that were missed, e.g. while waiting on the engine, are dropped rather than drawn back to back
+        self.next_frame += self.frame_time;
+        if self.next_frame <= now {
+            self.next_frame = now + self.frame_time;
+        }
+        true
+    }
+
+    /// How long after `now` the next frame is due
+    pub fn until_next_frame(&self, now: Instant) -> Duration {
+        self.next_frame.saturating_duration_since(now)
+    }
+}
+
+/// Whether a frame drawn from `next` would show the same as one drawn from `previous`, so it can be skipped.
+/// The engine's clock moves on every tick, so times are only compared to the precision they are shown with,
+/// and those that aren't shown at all, such as the tick and each producer's progress, are left out
+pub fn unchanged(previous: &Snapshot, next: &Snapshot) -> bool {
+    drawn(previous) == drawn(next)
+}
+
+/// The parts of `snapshot` that are drawn, with times rounded as they are shown: buffs and the offer to the second,
+/// action cooldowns to the tenth of a second and time played to the second
+fn drawn(snapshot: &Snapshot) -> (Snapshot, RunStatistics, RunStatistics, u32) {
+    let mut drawn = snapshot.clone();
+
+    drawn.tick = 0;
+    for producer in &mut drawn.producers {
+        producer.time_elapsed_ms = 0;
+    }
+    for action in &mut drawn.actions {
+        action.cooldown_remaining_ms = (action.cooldown_remaining_ms + 50) / 100;
+    }
+    for active in &mut drawn.buffs {
+        active.remaining_ms = active.remaining_ms.div_ceil(1000);
+    }
+    if let Some(active) = &mut drawn.offer {
+        active.remaining_ms = active.remaining_ms.div_ceil(1000);
+    }
+
+    // statistics also keep track of time that isn't shown, so only the parts that are shown are kept
+    let statistics = std::mem::take(&mut drawn.statistics);
+    let (mut run, mut lifetime) = (statistics.run, statistics.lifetime);
+    run.time_played_ms /= 1000;
+    lifetime.time_played_ms /= 1000;
+
+    (drawn, run, lifetime, statistics.prestiges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content;
+    use crate::continuum::{EngineConfig, EngineMode, Simulation};
+
+    fn simulation() -> Simulation {
+        let config = EngineConfig {
+            mode: EngineMode::SingleThreaded,
+            tick_timeout_ms: 50,
+            starting_funds: 0.0,
+            sell_refund_ratio: 0.5,
+            prestige_bonus: 0.1,
+            seed: 1,
+            world_event_interval_ms: 0,
+        };
+        Simulation::new(config, &content::currencies(), &content::producers()).unwrap()
+    }
+
+    #[test]
+    fn idle_ticks_are_skipped() {
+        let mut simulation = simulation();
+        simulation.step();
+        let previous = simulation.snapshot();
+        simulation.step();
+        let next = simulation.snapshot();
+
+        // the tick and time played have moved on, but not so that anything drawn changes
+        assert_ne!(previous, next);
+        assert!(unchanged(&previous, &next));
+    }
+
+    #[test]
+    fn shown_times_are_drawn() {
+        let mut simulation = simulation();
+        let previous = simulation.snapshot();
+        for _ in 0..20 {
+            simulation.step();
+        }
+
+        // a second has been played
+        assert!(!unchanged(&previous, &simulation.snapshot()));
+    }
+}
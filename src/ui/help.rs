@@ -0,0 +1,106 @@
+use tui::{
+    Frame,
+    widgets::{Widget, Block, Borders, Table, Row},
+    layout::{Constraint, Rect},
+    style::{Style, Color},
+};
+use crate::continuum::{EngineCommand, Snapshot};
+use super::bindings::Action;
+use super::screen::{Screen, ScreenContext};
+use super::TerminalBackend;
+
+/// How the game works, shown in the help after the keys
+const MECHANICS: [&str; 6] = [
+    "Buying producers",
+    "  Each producer makes a product every few seconds, which is sold straight away for one of the currencies. Every producer you own makes the price of the next one go up by a fixed percentage, so it pays to spread out across producers rather than buying many of the same one.",
+    "Selling",
+    "  Selling a producer refunds a fraction of what you paid for the last one bought. It frees up funds to buy something better, but you always get back less than you spent.",
+    "Prestige",
+    "  Prestige starts the game over: every producer, product, upgrade, buff and coin is lost, but each prestige level permanently boosts everything you make by a percentage. Lifetime statistics carry over.",
+];
+
+/// Lists what every key does
+#[derive(Default)]
+pub struct HelpScreen;
+
+impl HelpScreen {
+    /// One row per bound action that applies to this game: what it does, its keys, and its name in the bindings file
+    pub(super) fn describe_bindings(context: &ScreenContext) -> Vec<[String; 3]> {
+        context.bindings.iter()
+            .filter_map(|(action, keys)| {
+                let description = Self::describe_action(context, action)?;
+                let keys = keys.iter().map(|k| k.to_string()).collect::<Vec<_>>().join(", ");
+                Some([description, keys, action.to_string()])
+            })
+            .collect()
+    }
+
+    /// Everything shown in the help: the keys, then how the game works
+    pub(super) fn help_text(context: &ScreenContext) -> Vec<String> {
+        let mut lines = vec!["Keys".to_string()];
+        for [description, keys, _] in Self::describe_bindings(context) {
+            lines.push(format!("  {:<20} {}", keys, description));
+        }
+        for line in MECHANICS.iter() {
+            // a blank line before each heading
+            if !line.starts_with(' ') {
+                lines.push(String::new());
+            }
+            lines.push(line.to_string());
+        }
+        lines
+    }
+
+    /// What `action` does, or `None` if it doesn't apply to this game, e.g. buying a producer that doesn't exist
+    fn describe_action(context: &ScreenContext, action: Action) -> Option<String> {
+        let snapshot = context.snapshot;
+        let description = match action {
+            Action::Quit => "Quit the game".to_string(),
+            Action::SaveAndQuit => "When quitting, save the replay and statistics first".to_string(),
+            Action::QuitWithoutSaving => "When quitting, quit without saving".to_string(),
+            Action::ToggleHelp => "Open or close the help".to_string(),
+            Action::CycleLogLevel => "Change how much is written to the log file".to_string(),
+            Action::ShowScreen(i) => format!("Show the {} tab", context.screen_titles.get(i)?),
+            Action::AcceptOffer => "Accept the trade on offer".to_string(),
+            Action::Buy(id) => format!("Buy a {}", snapshot.producer(id)?.name),
+            Action::Sell(id) => format!("Sell a {}", snapshot.producer(id)?.name),
+            Action::Perform(id) => snapshot.action(id)?.name.clone(),
+            Action::BuyFrenzy => "Buy the frenzy buff".to_string(),
+            Action::Exchange => "Exchange one currency for another".to_string(),
+            Action::Prestige => "Prestige: start over with a permanent bonus".to_string(),
+            Action::ScrollUp => "Scroll the buttons up".to_string(),
+            Action::ScrollDown => "Scroll the buttons down".to_string(),
+            Action::NextPage => "Turn to the next page of the help".to_string(),
+            Action::PreviousPage => "Turn to the previous page of the help".to_string(),
+            Action::Close => "Close the help, or cancel quitting".to_string(),
+        };
+        Some(description)
+    }
+}
+
+impl Screen for HelpScreen {
+    fn title(&self) -> &str {
+        "Help"
+    }
+
+    fn render(&mut self, f: &mut Frame<TerminalBackend>, context: &ScreenContext, area: Rect) -> Vec<(Rect, Action)> {
+        let rows = Self::describe_bindings(context);
+
+        Table::new(
+            ["Action", "Keys", "Name in bindings file"].iter(),
+            rows.iter().map(|row| Row::Data(row.iter()))
+        )
+        .block(Block::default().title("[ Key Bindings ]").borders(Borders::ALL))
+        .header_style(Style::default().fg(Color::Cyan))
+        .widths(&[Constraint::Length(45), Constraint::Length(20), Constraint::Length(25)])
+        .style(Style::default().fg(Color::White))
+        .column_spacing(1)
+        .render(f, area);
+
+        Vec::new()
+    }
+
+    fn handle_action(&mut self, _action: Action, _snapshot: &Snapshot) -> Option<EngineCommand> {
+        None
+    }
+}
@@ -0,0 +1,207 @@
+use tui::{
+    Frame,
+    widgets::{Widget, Block, Borders, Paragraph, Text, Table, Row},
+    layout::{Layout, Constraint, Direction, Rect},
+    style::{Style, Color},
+};
+use crate::continuum::{Buff, CurrencyId, EngineCommand, Snapshot};
+use crate::custom_widgets::Button;
+use crate::logging::{Level, Target};
+use super::screen::{Screen, ScreenContext};
+use super::bindings::Action;
+use super::{TerminalBackend, UI};
+
+/// How much of the first currency `Action::Exchange` exchanges at a time
+const EXCHANGE_AMOUNT: f64 = 10.0;
+
+/// Buttons are at least this wide, and share out any space left over on their row
+const BUTTON_WIDTH: u16 = 40;
+const BUTTON_HEIGHT: u16 = 3;
+/// Rows of buttons shown at once, past which the rest are scrolled to
+const VISIBLE_BUTTON_ROWS: u16 = 3;
+
+/// A button to draw in the actions panel
+struct ActionButton {
+    text: String,
+    style: Style,
+    action: Action,
+}
+
+/// The main screen of the game: the products made so far, the producers and actions to buy and use, and the game log
+pub struct ProducersScreen {
+    /// The buff bought by `Action::BuyFrenzy`
+    frenzy: Buff,
+    /// The currencies `Action::Exchange` exchanges from and to
+    exchange: (CurrencyId, CurrencyId),
+    /// The first row of buttons shown, when there are more than fit
+    scroll: u16,
+}
+
+impl ProducersScreen {
+    pub fn new(frenzy: Buff, exchange: (CurrencyId, CurrencyId)) -> Self {
+        ProducersScreen { frenzy, exchange, scroll: 0 }
+    }
+
+    fn render_products(f: &mut Frame<TerminalBackend>, products: &[(String, String)], area: Rect) {
+        if !products.is_empty() {
+            Table::new(
+                ["Item", "Qty"].iter(),
+                products.iter().map(|(label, value)| {
+                    Row::Data(vec![label, value].into_iter())
+                })
+            )
+            .block(Block::default().title("[ Products ]").borders(Borders::ALL))
+            .header_style(Style::default().fg(Color::Cyan))
+            .widths(&[Constraint::Length(9), Constraint::Min(0)])
+            .style(Style::default().fg(Color::White))
+            .column_spacing(1)
+            .render(f, area);
+        } else {
+            Block::default()
+                .borders(Borders::ALL)
+                .title("[ Products ]")
+                .render(f, area);
+        }
+    }
+
+    /// One button for each producer and then each manual action
+    fn action_buttons(context: &ScreenContext) -> Vec<ActionButton> {
+        let snapshot = context.snapshot;
+        let dimmed = Style::default().fg(Color::DarkGray);
+        // e.g. "[a] ", or nothing if the action isn't bound to a key
+        let hotkey = |action: Action| context.bindings.keys(action).first()
+            .map_or(String::new(), |key| format!("[{}] ", key));
+
+        let producers = snapshot.producers.iter().map(|producer| {
+            ActionButton {
+                text: format!("{}{} x{}: {}", hotkey(Action::Buy(producer.id)), producer.name, producer.count,
+                    UI::describe_price(context.registry, &producer.cost)),
+                style: if snapshot.can_afford(&producer.cost) {
+                    Style::default().fg(Color::Black).bg(Color::Cyan)
+                } else {
+                    dimmed
+                },
+                action: Action::Buy(producer.id),
+            }
+        });
+        let actions = snapshot.actions.iter().map(|action| ActionButton {
+            text: match action.cooldown_remaining_ms {
+                0 => format!("{}{} (+{:.2})", hotkey(Action::Perform(action.id)), action.name, action.next_yield),
+                ms => format!("{}{} ({:.1}s)", hotkey(Action::Perform(action.id)), action.name, ms as f64 / 1000.0),
+            },
+            style: if action.cooldown_remaining_ms == 0 {
+                Style::default().fg(Color::Black).bg(Color::Green)
+            } else {
+                dimmed
+            },
+            action: Action::Perform(action.id),
+        });
+        producers.chain(actions).collect()
+    }
+
+    /// Lays the buttons out in rows as wide as `area`, scrolled to `self.scroll`
+    fn render_actions(&mut self, f: &mut Frame<TerminalBackend>, context: &ScreenContext, area: Rect) -> Vec<(Rect, Action)> {
+        let action_buttons = Self::action_buttons(context);
+
+        let columns = (area.width.saturating_sub(2) / BUTTON_WIDTH).max(1);
+        let rows = (action_buttons.len() as u16).div_ceil(columns);
+        let wanted_rows = rows.clamp(1, VISIBLE_BUTTON_ROWS);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(wanted_rows * BUTTON_HEIGHT + 2), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        // the panel is squeezed when the terminal is short, so only the rows that fit inside it are shown
+        let inner_rect = Block::default().borders(Borders::ALL).inner(chunks[0]);
+        let visible_rows = wanted_rows.min(rows).min(inner_rect.height / BUTTON_HEIGHT);
+        self.scroll = self.scroll.min(rows - visible_rows);
+
+        let title = if rows > visible_rows && visible_rows > 0 {
+            format!("[ Actions - rows {}-{} of {} ]", self.scroll + 1, self.scroll + visible_rows, rows)
+        } else {
+            "[ Actions ]".to_string()
+        };
+        Block::default()
+            .borders(Borders::ALL)
+            .title(&title)
+            .render(f, chunks[0]);
+
+        let button_width = inner_rect.width / columns;
+        let mut buttons = Vec::new();
+        for (i, button) in action_buttons.iter().enumerate() {
+            let (row, column) = (i as u16 / columns, i as u16 % columns);
+            if button_width == 0 || row < self.scroll || row >= self.scroll + visible_rows {
+                continue;
+            }
+            let button_area = Rect {
+                x: inner_rect.x + column * button_width,
+                y: inner_rect.y + (row - self.scroll) * BUTTON_HEIGHT,
+                width: button_width,
+                height: BUTTON_HEIGHT,
+            };
+            Button::default()
+                .text(&button.text)
+                .style(button.style)
+                .render(f, button_area);
+            buttons.push((button_area, button.action));
+        }
+        log!(Level::Trace, Target::Ui, "buttons = {:?}", buttons);
+
+        Self::render_game_log(f, context.game_log, chunks[1]);
+        buttons
+    }
+
+    fn render_game_log(f: &mut Frame<TerminalBackend>, game_log: &[String], area: Rect) {
+        let visible = area.height.saturating_sub(2) as usize;
+
+        // newest entries first
+        let lines = game_log.iter().rev().take(visible)
+            .map(|line| Text::raw(format!("{}\n", line)))
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines.iter())
+            .block(Block::default().borders(Borders::ALL).title("[ Game Log ]"))
+            .render(f, area);
+    }
+}
+
+impl Screen for ProducersScreen {
+    fn title(&self) -> &str {
+        "Producers"
+    }
+
+    fn render(&mut self, f: &mut Frame<TerminalBackend>, context: &ScreenContext, area: Rect) -> Vec<(Rect, Action)> {
+        let products = context.snapshot.products.iter().map(|p| {
+            (p.name.clone(), format!("{}", p.quantity))
+        }).collect::<Vec<_>>();
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(50), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        Self::render_products(f, &products, chunks[0]);
+        self.render_actions(f, context, chunks[1])
+    }
+
+    fn handle_action(&mut self, action: Action, snapshot: &Snapshot) -> Option<EngineCommand> {
+        match action {
+            Action::Buy(id) => snapshot.producer(id).map(|p| EngineCommand::Buy { id: p.id, count: 1 }),
+            Action::Sell(id) => snapshot.producer(id).map(|p| EngineCommand::Sell { id: p.id, count: 1 }),
+            Action::Perform(id) => snapshot.action(id).map(|a| EngineCommand::PerformAction(a.id)),
+            Action::BuyFrenzy => Some(EngineCommand::BuyBuff(self.frenzy.clone())),
+            Action::Exchange => Some(EngineCommand::Exchange { from: self.exchange.0, to: self.exchange.1, amount: EXCHANGE_AMOUNT }),
+            Action::ScrollUp => {
+                self.scroll = self.scroll.saturating_sub(1);
+                None
+            }
+            // clamped to the last row when the buttons are next laid out
+            Action::ScrollDown => {
+                self.scroll += 1;
+                None
+            }
+            _ => None,
+        }
+    }
+}
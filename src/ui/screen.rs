@@ -0,0 +1,30 @@
+use tui::{layout::Rect, Frame};
+
+use crate::continuum::{EngineCommand, Registry, Snapshot};
+use super::bindings::{Action, KeyBindings};
+use super::TerminalBackend;
+
+/// Everything a screen is drawn from, gathered once per frame
+pub struct ScreenContext<'a> {
+    pub snapshot: &'a Snapshot,
+    /// Knows the names of everything, including products that haven't been made this run
+    pub registry: &'a Registry,
+    /// The messages in the game log, oldest first
+    pub game_log: &'a [String],
+    pub bindings: &'a KeyBindings,
+    /// The title of every screen, in the order of their tabs
+    pub screen_titles: &'a [String],
+}
+
+/// One of the tabs of the UI, which draws the body of the window while it is selected and handles the actions
+/// taken on it
+pub trait Screen {
+    /// The name shown on the screen's tab
+    fn title(&self) -> &str;
+
+    /// Draws the screen into `area`. Returns where any buttons were drawn, with the action that clicking each takes
+    fn render(&mut self, f: &mut Frame<TerminalBackend>, context: &ScreenContext, area: Rect) -> Vec<(Rect, Action)>;
+
+    /// Handles `action` being taken while the screen is shown, returning the command it asks the engine for, if any
+    fn handle_action(&mut self, action: Action, snapshot: &Snapshot) -> Option<EngineCommand>;
+}
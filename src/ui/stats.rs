@@ -0,0 +1,86 @@
+use tui::{
+    Frame,
+    widgets::{Widget, Block, Borders, Table, Row},
+    layout::{Constraint, Rect},
+    style::{Style, Color},
+};
+use crate::continuum::{EngineCommand, Registry, Snapshot, Statistics};
+use crate::continuum::statistics::{RunStatistics, MILESTONES};
+use super::screen::{Screen, ScreenContext};
+use super::bindings::Action;
+use super::TerminalBackend;
+
+/// Statistics for the current run and over every game
+#[derive(Default)]
+pub struct StatsScreen;
+
+impl StatsScreen {
+    /// One row per statistic, with its value for the current run and over every run
+    fn describe_statistics(registry: &Registry, statistics: &Statistics) -> Vec<[String; 3]> {
+        let (run, lifetime) = (&statistics.run, &statistics.lifetime);
+        let row = |label: String, value: &dyn Fn(&RunStatistics) -> String| [label, value(run), value(lifetime)];
+
+        let mut rows = vec![
+            row("Time played".to_string(), &|s| Self::describe_duration(s.time_played_ms)),
+            ["Prestiges".to_string(), String::new(), statistics.prestiges.to_string()],
+            row("Producers bought".to_string(), &|s| s.producers_bought.to_string()),
+            row("Manual actions".to_string(), &|s| s.actions_performed.to_string()),
+        ];
+        for product in registry.product_ids() {
+            let name = registry.product_name(product);
+            rows.push(row(format!("{} made", name), &|s| format!("{:.2}", s.produced.get(&product).copied().unwrap_or(0.0))));
+            rows.push(row(format!("{} made by hand", name), &|s| format!("{:.2}", s.produced_by_hand.get(&product).copied().unwrap_or(0.0))));
+        }
+        for currency in registry.currency_ids() {
+            let name = registry.currency_name(currency);
+            rows.push(row(format!("{} earned", name), &|s| format!("{:.2}", s.earned.amount(currency))));
+            rows.push(row(format!("{} spent", name), &|s| format!("{:.2}", s.spent.amount(currency))));
+            rows.push(row(format!("Peak {} per second", name), &|s| format!("{:.2}", s.peak_rate.amount(currency))));
+        }
+        if let Some(primary) = registry.currency_ids().next() {
+            for (i, target) in MILESTONES.iter().enumerate() {
+                rows.push(row(format!("Fastest to {} {}", target, registry.currency_name(primary)), &|s| {
+                    s.milestones_ms.get(i).copied().flatten().map_or("-".to_string(), Self::describe_duration)
+                }));
+            }
+        }
+        rows
+    }
+
+    /// e.g. "1h 02m 03s"
+    fn describe_duration(ms: u64) -> String {
+        let secs = ms / 1000;
+        match (secs / 3600, secs / 60 % 60, secs % 60) {
+            (0, 0, s) => format!("{}s", s),
+            (0, m, s) => format!("{}m {:02}s", m, s),
+            (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+        }
+    }
+}
+
+impl Screen for StatsScreen {
+    fn title(&self) -> &str {
+        "Stats"
+    }
+
+    fn render(&mut self, f: &mut Frame<TerminalBackend>, context: &ScreenContext, area: Rect) -> Vec<(Rect, Action)> {
+        let stats = Self::describe_statistics(context.registry, &context.snapshot.statistics);
+
+        Table::new(
+            ["Statistic", "This run", "Lifetime"].iter(),
+            stats.iter().map(|row| Row::Data(row.iter()))
+        )
+        .block(Block::default().title("[ Stats ]").borders(Borders::ALL))
+        .header_style(Style::default().fg(Color::Cyan))
+        .widths(&[Constraint::Length(40), Constraint::Length(20), Constraint::Length(20)])
+        .style(Style::default().fg(Color::White))
+        .column_spacing(1)
+        .render(f, area);
+
+        Vec::new()
+    }
+
+    fn handle_action(&mut self, _action: Action, _snapshot: &Snapshot) -> Option<EngineCommand> {
+        None
+    }
+}